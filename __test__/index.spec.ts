@@ -1,9 +1,20 @@
+import fs from 'node:fs';
 import test from 'ava';
 import serial from '../index';
+import type { PortErrorKind, PortSettings } from '../index';
 
 test('module loads and exposes expected symbols', t => {
   t.truthy(serial, 'native binding should be defined');
-  const expected = ['OpenPort', 'AvailablePort', 'DataBits', 'FlowControl', 'listPorts', 'Parity', 'StopBits'];
+  const expected = [
+    'OpenPort',
+    'AvailablePort',
+    'DataBits',
+    'FlowControl',
+    'listPorts',
+    'Parity',
+    'PortErrorKind',
+    'StopBits',
+  ];
   for (const key of expected) {
     t.truthy(Object.prototype.hasOwnProperty.call(serial, key), `${key} should be exported`);
   }
@@ -14,8 +25,275 @@ test('listPorts returns an array', async t => {
   t.true(Array.isArray(ports), 'listPorts should return an array');
 });
 
+test('listPortsFiltered only returns USB ports when filtering by vid', t => {
+  const ports = serial.listPortsFiltered({ vid: 0x0403 });
+  t.true(Array.isArray(ports), 'listPortsFiltered should return an array');
+  for (const port of ports) {
+    t.is(port.usb?.vid, 0x0403);
+  }
+});
+
+test('CRC helpers match the standard check values', t => {
+  const check = Buffer.from('123456789');
+  t.is(serial.computeCrc16Ccitt(check), 0x29b1);
+  t.is(serial.computeCrc32(check), 0xcbf43926);
+  t.is(serial.computeCrc16Ccitt(Buffer.alloc(0)), 0xffff);
+  t.is(serial.computeCrc32(Buffer.alloc(0)), 0);
+});
+
 test('AvailablePort prototype exposes open method', t => {
   t.true(typeof serial.AvailablePort === 'function', 'AvailablePort should be a constructor');
   const hasOpen = typeof serial.AvailablePort?.prototype?.open === 'function';
   t.true(hasOpen, 'AvailablePort.prototype.open should be a function');
 });
+
+test('OpenPort prototype exposes buffer control methods', t => {
+  t.true(typeof serial.OpenPort === 'function', 'OpenPort should be a constructor');
+  for (const key of ['clearInput', 'clearOutput', 'drain']) {
+    t.true(typeof serial.OpenPort?.prototype?.[key] === 'function', `OpenPort.prototype.${key} should be a function`);
+  }
+});
+
+test('openLoopback delivers writes back one frame at a time', async t => {
+  if (process.platform === 'win32') {
+    t.pass('loopback ports are only available on Unix');
+    return;
+  }
+
+  const frames: string[] = [];
+  const received = new Promise<void>(resolve => {
+    const port = serial.openLoopback(
+      data => {
+        frames.push(data.toString());
+        if (frames.length === 2) {
+          port.close();
+          resolve();
+        }
+      },
+      err => t.fail(err.message),
+      { framing: { type: 'Delimiter', bytes: Buffer.from('\n') } },
+    );
+    port.write(Buffer.from('hello\nwor'));
+    port.write(Buffer.from('ld\n'));
+  });
+
+  await received;
+  t.deepEqual(frames, ['hello', 'world']);
+});
+
+test('openLoopback round-trips COBS packets containing zeros', async t => {
+  if (process.platform === 'win32') {
+    t.pass('loopback ports are only available on Unix');
+    return;
+  }
+
+  const packet = Buffer.from([0x11, 0x00, 0x00, 0x22]);
+  const received = await new Promise<Buffer>(resolve => {
+    const port = serial.openLoopback(
+      data => {
+        port.close();
+        resolve(data as Buffer);
+      },
+      err => t.fail(err.message),
+      { framing: { type: 'Cobs' } },
+    );
+    port.write(packet);
+  });
+
+  t.deepEqual(received, packet);
+});
+
+test('openLoopback decodes text lines when an encoding is set', async t => {
+  if (process.platform === 'win32') {
+    t.pass('loopback ports are only available on Unix');
+    return;
+  }
+
+  const lines: Array<Buffer | string> = [];
+  await new Promise<void>(resolve => {
+    const port = serial.openLoopback(
+      data => {
+        lines.push(data);
+        if (lines.length === 2) {
+          port.close();
+          resolve();
+        }
+      },
+      err => t.fail(err.message),
+      { encoding: serial.TextEncoding.Utf8 },
+    );
+    port.write(Buffer.from('h\u00e9llo\r\nworld\n'));
+  });
+
+  t.deepEqual(lines, ['h\u00e9llo', 'world']);
+});
+
+test('openPtyPair receives what is written to the other end', async t => {
+  if (process.platform === 'win32') {
+    t.pass('pty pairs are only available on Unix');
+    return;
+  }
+
+  let resolve!: (data: Buffer) => void;
+  const received = new Promise<Buffer>(r => (resolve = r));
+  const port = serial.openPtyPair(data => resolve(data as Buffer), err => t.fail(err.message));
+
+  const fd = fs.openSync(port.path, 'r+');
+  fs.writeSync(fd, Buffer.from('ping'));
+  t.deepEqual(await received, Buffer.from('ping'));
+
+  fs.closeSync(fd);
+  port.close();
+});
+
+test('discardMsAfterOpen drops what arrives right after opening', async t => {
+  if (process.platform === 'win32') {
+    t.pass('loopback ports are only available on Unix');
+    return;
+  }
+
+  const chunks: Buffer[] = [];
+  await new Promise<void>(resolve => {
+    const port = serial.openLoopback(
+      data => {
+        chunks.push(data as Buffer);
+        if (Buffer.concat(chunks).toString().endsWith('late')) {
+          port.close();
+          resolve();
+        }
+      },
+      err => t.fail(err.message),
+      { discardMsAfterOpen: 200 },
+    );
+    port.write(Buffer.from('early'));
+    setTimeout(() => port.write(Buffer.from('late')), 400);
+  });
+
+  t.is(Buffer.concat(chunks).toString(), 'late');
+});
+
+test('lock hands out the port and refuses a closed one', async t => {
+  if (process.platform === 'win32') {
+    t.pass('loopback ports are only available on Unix');
+    return;
+  }
+
+  const chunks: Buffer[] = [];
+  let resolve!: () => void;
+  const received = new Promise<void>(r => (resolve = r));
+  const port = serial.openLoopback(
+    data => {
+      chunks.push(data as Buffer);
+      if (Buffer.concat(chunks).toString() === 'locked') {
+        resolve();
+      }
+    },
+    err => t.fail(err.message),
+  );
+
+  const lock = await port.lock();
+  t.true(lock.locked);
+  lock.write(Buffer.from('locked'));
+  lock.unlock();
+  t.false(lock.locked);
+  await received;
+
+  port.close();
+  t.throws(() => port.lock(), { message: /port is closed/ });
+});
+
+test('1.5 stop bits are refused without five data bits', t => {
+  if (process.platform === 'win32') {
+    t.pass('loopback ports are only available on Unix');
+    return;
+  }
+
+  t.throws(
+    () =>
+      serial.openLoopback(
+        () => {},
+        () => {},
+        { stopBits: serial.StopBits.OnePointFive, dataBits: serial.DataBits.Eight },
+      ),
+    { message: '1.5 stop bits are unsupported with dataBits other than Five' },
+  );
+
+  const port = serial.openLoopback(
+    () => {},
+    err => t.fail(err.message),
+    { stopBits: serial.StopBits.OnePointFive, dataBits: serial.DataBits.Five },
+  );
+  port.close();
+});
+
+// Write `chunks` to the other end of a pty pair opened with `settings`, pausing between them so each arrives in a
+// read of its own, and collect what reaches the callbacks
+async function framePtyWrites(settings: PortSettings, chunks: Buffer[]) {
+  const frames: Buffer[] = [];
+  const errors: PortErrorKind[] = [];
+  const port = serial.openPtyPair(
+    data => frames.push(data as Buffer),
+    err => errors.push(err.kind),
+    settings,
+  );
+  const fd = fs.openSync(port.path, 'r+');
+  for (const chunk of chunks) {
+    fs.writeSync(fd, chunk);
+    await new Promise(resolve => setTimeout(resolve, 50));
+  }
+  await new Promise(resolve => setTimeout(resolve, 100));
+
+  fs.closeSync(fd);
+  port.close();
+  return { frames, errors };
+}
+
+test('Delimiters framing joins a frame split across reads', async t => {
+  if (process.platform === 'win32') {
+    t.pass('pty pairs are only available on Unix');
+    return;
+  }
+
+  const { frames, errors } = await framePtyWrites(
+    { framing: { type: 'Delimiters', bytes: [Buffer.from('\r\n'), Buffer.from('\n')] } },
+    [Buffer.from('hel'), Buffer.from('lo\r'), Buffer.from('\nworld\n')],
+  );
+
+  t.deepEqual(frames.map(String), ['hello', 'world']);
+  t.deepEqual(errors, []);
+});
+
+test('a partial frame past maxFrameSize is reported and cut up', async t => {
+  if (process.platform === 'win32') {
+    t.pass('pty pairs are only available on Unix');
+    return;
+  }
+
+  const { frames, errors } = await framePtyWrites(
+    {
+      framing: { type: 'Delimiter', bytes: Buffer.from('\n') },
+      maxFrameSize: 4,
+      frameOverflow: serial.FrameOverflow.Deliver,
+    },
+    [Buffer.from('abcdefgh'), Buffer.from('\nok\n')],
+  );
+
+  t.deepEqual(frames.map(String), ['abcd', 'efgh', 'ok']);
+  t.deepEqual(errors, [serial.PortErrorKind.FrameOverflow]);
+});
+
+test('ModbusRtu framing drops frames with a bad CRC', async t => {
+  if (process.platform === 'win32') {
+    t.pass('pty pairs are only available on Unix');
+    return;
+  }
+
+  // read one holding register of unit 1, the CRC of the second copy is the right one
+  const { frames, errors } = await framePtyWrites({ framing: { type: 'ModbusRtu', gapMs: 20 } }, [
+    Buffer.from([0x01, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00]),
+    Buffer.from([0x01, 0x03, 0x00, 0x00, 0x00, 0x01, 0x84, 0x0a]),
+  ]);
+
+  t.deepEqual(frames, [Buffer.from([0x01, 0x03, 0x00, 0x00, 0x00, 0x01])]);
+  t.deepEqual(errors, [serial.PortErrorKind.InvalidFrame]);
+});
@@ -8,6 +8,62 @@ b.add('list ports', () => {
   serial.listPorts();
 });
 
+// Push 1 MiB through a loopback port, which exercises the read path end to end
+if (process.platform !== 'win32') {
+  const chunk = Buffer.alloc(64 * 1024, 0x55);
+  b.add('loopback 1 MiB', async () => {
+    await new Promise<void>((resolve, reject) => {
+      let received = 0;
+      const port = serial.openLoopback(
+        data => {
+          received += data.length;
+          if (received >= 16 * chunk.length) {
+            port.close();
+            resolve();
+          }
+        },
+        err => reject(new Error(err.message)),
+        { readBufferSize: 64 * 1024 },
+      );
+      for (let i = 0; i < 16; i++) {
+        port.write(chunk);
+      }
+    });
+  });
+}
+
+// Feed a loopback port 1 MiB at 10 MB/s, a steady stream rather than a burst, and wait for the last byte
+if (process.platform !== 'win32') {
+  const chunk = Buffer.alloc(16 * 1024, 0x55);
+  const chunks = 64;
+  const chunkIntervalMs = (chunk.length / 10_000_000) * 1000;
+  b.add('loopback 1 MiB at 10 MB/s', async () => {
+    await new Promise<void>((resolve, reject) => {
+      let received = 0;
+      let sent = 0;
+      const port = serial.openLoopback(
+        data => {
+          received += data.length;
+          if (received >= chunks * chunk.length) {
+            clearInterval(timer);
+            port.close();
+            resolve();
+          }
+        },
+        err => reject(new Error(err.message)),
+      );
+      const started = performance.now();
+      // timers are coarser than one chunk's worth of time, so catch up to the schedule on every tick
+      const timer = setInterval(() => {
+        const due = Math.min(chunks, Math.floor((performance.now() - started) / chunkIntervalMs) + 1);
+        for (; sent < due; sent++) {
+          port.write(chunk);
+        }
+      }, 1);
+    });
+  });
+}
+
 await b.run();
 
 console.table(b.table());
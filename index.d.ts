@@ -1,21 +1,395 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+/** Cancels the `read_once` or `transact` it is passed to, e.g. from an `AbortSignal` listener */
+export declare class AbortToken {
+  constructor();
+  /** Reject the request this token was passed to with a cancellation error */
+  abort(): void;
+  get aborted(): boolean;
+}
+
 export declare class AvailablePort {
   readonly path: string;
   readonly type: string;
   readonly usb?: UsbInfo;
+  /** the /dev/serial/by-id link to the device, Linux only */
+  readonly byIdPath?: string;
+  /** the /dev/serial/by-path link to the device, Linux only */
+  readonly byPathPath?: string;
   open(
-    onDataReceived: (arg: Buffer) => void,
-    onError: (err: Error | null) => void,
+    onDataReceived: (arg: Buffer | string | TimestampedData) => void,
+    onError: (arg: PortError) => void,
     settings?: PortSettings | undefined | null,
+    onDisconnect?: ((arg: PortError) => void) | undefined | null,
+    onReconnect?: ((arg: string) => void) | undefined | null,
+    onReady?: (() => void) | undefined | null,
   ): OpenPort;
+  /** Like `open`, but opens the port off the JS thread, so several ports can be opened at once. */
+  openAsync(
+    onDataReceived: (arg: Buffer | string | TimestampedData) => void,
+    onError: (arg: PortError) => void,
+    settings?: PortSettings | undefined | null,
+    onDisconnect?: ((arg: PortError) => void) | undefined | null,
+    onReconnect?: ((arg: string) => void) | undefined | null,
+    onReady?: (() => void) | undefined | null,
+  ): Promise<OpenPort>;
+  /**
+   * An id for the device that survives reboots and replugging, unlike the path.
+   *
+   * Built from the VID, PID and serial number of USB devices that have one, otherwise the
+   * `/dev/serial/by-id` link on Linux. None when the device can't be told apart from others.
+   */
+  uniqueId(): string | null;
+  /**
+   * A label to show users, e.g. "Arduino LLC Arduino Uno (8573531303)" instead of the path.
+   *
+   * Made up of the USB manufacturer, product and serial number where known, otherwise the path. Two
+   * identical devices without serial numbers get the same name.
+   */
+  displayName(): string;
+  /** Find out what the port supports, without opening it */
+  capabilities(): PortCapabilities;
 }
 
 export declare class OpenPort {
   write(data: Buffer): void;
+  /**
+   * Encode `text` and queue it like `write`.
+   *
+   * Uses `encoding`, or the port's `encoding` setting, falling back to UTF-8. Fails if the text
+   * contains a character the encoding can't represent.
+   */
+  writeString(text: string, encoding?: TextEncoding | undefined | null): void;
+  /**
+   * Queue a write ahead of every regular write still waiting to go out, e.g. an abort byte.
+   *
+   * Priority writes go out in the order they were queued, and never count against `write_queue_capacity`.
+   */
+  writePriority(data: Buffer): void;
+  /**
+   * Queue several writes at once, they go out back to back in order.
+   *
+   * Cheaper than calling `write` for each chunk, and takes a single slot in a bounded write queue.
+   */
+  writeMany(chunks: Array<Buffer>): void;
+  /**
+   * Queue a write that goes out one byte at a time, waiting `byte_delay_us` microseconds after each byte
+   * has left the port, for receivers too slow to take data at full speed.
+   *
+   * Each pause is a sleep on the write thread, which at microsecond resolution costs CPU time and
+   * usually overshoots by tens of microseconds.
+   */
+  writeSlow(data: Buffer, byteDelayUs: number): void;
+  /**
+   * Queue 9-bit words for a multidrop bus, sending the 9th bit as the parity bit.
+   *
+   * A word goes out with the 9th bit set when it has any bit of `address_mask` set (defaults to 0x100),
+   * the low 8 bits are the data. Needs a port opened with mark or space parity, so Linux or Windows only.
+   */
+  write9Bit(data: Array<number>, addressMask?: number | undefined | null): void;
+  /** Whether `write` would fail right now because the bounded write queue is full */
+  writeWouldBlock(): boolean;
+  /**
+   * How many entries the write thread still has to work through, priority writes included.
+   *
+   * Each `write`, `write_many` batch, transaction and queued line change counts once.
+   */
+  pendingWrites(): number;
+  /** Queue a write, waiting off the JS thread for room if the write queue is full */
+  writeAsync(data: Buffer): Promise<void>;
+  /**
+   * Queue a write and resolve once it has been handed to the OS.
+   *
+   * Write failures reject the returned promise instead of going to `onError`.
+   */
+  writeAndWait(data: Buffer): Promise<void>;
+  /**
+   * Change how long each read waits for data before the read thread checks for other work.
+   *
+   * 0 makes reads return immediately, so the read thread polls back to back unless `max_idle_poll_ms` is set.
+   */
+  setTimeout(timeoutMs: number): void;
+  /**
+   * Change the baud rate of the open port without reopening it.
+   *
+   * The read and write handles are clones of the same OS handle, so the
+   * change applies to both directions.
+   */
+  setBaudRate(baud: number): void;
+  /**
+   * Change the flow control of the open port without reopening it, e.g. once the peer is known.
+   *
+   * Like the baud rate this is a setting of the OS handle both directions share.
+   */
+  setFlowControl(flowControl: FlowControl): void;
+  /** Read the state of the Clear To Send line */
+  readClearToSend(): boolean;
+  /**
+   * Wait for the device to assert CTS, resolving false if it didn't within `timeout_ms`.
+   *
+   * Resolves true straight away if CTS is already asserted.
+   */
+  waitForCts(timeoutMs: number): Promise<boolean>;
+  /** Read the state of the Data Set Ready line */
+  readDataSetReady(): boolean;
+  /** Read the state of the Carrier Detect line */
+  readCarrierDetect(): boolean;
+  /** Read the state of the Ring Indicator line */
+  readRingIndicator(): boolean;
+  /** Set the Request To Send line, ordered after any writes already queued */
+  setRts(level: boolean): void;
+  /** Set the Data Terminal Ready line, ordered after any writes already queued */
+  setDtr(level: boolean): void;
+  /** Drop DTR for the given milliseconds and raise it again, e.g. to reset a board, ordered after any writes already queued */
+  pulseDtr(lowMs: number): void;
+  /** Assert a break condition for the given milliseconds, ordered after any writes already queued */
+  sendBreak(durationMs: number): void;
+  /** Assert or clear the break condition, ordered after any writes already queued */
+  setBreak(asserted: boolean): void;
+  /** Discard any bytes received by the OS but not yet read */
+  clearInput(): void;
+  /** Discard any bytes handed to the OS but not yet transmitted */
+  clearOutput(): void;
+  /** Block until every write queued so far has been transmitted */
+  drain(): void;
+  /** Number of bytes received by the OS and waiting to be read */
+  bytesToRead(): number;
+  /**
+   * Number of bytes sitting in the OS output buffer.
+   *
+   * Writes still queued inside this addon are not included.
+   */
+  bytesToWrite(): number;
+  /**
+   * File descriptor of the port on Unix, for ioctls this addon doesn't wrap.
+   *
+   * The descriptor stays owned by the read thread: don't close it, don't read from it, and
+   * don't hold on to it across a reconnect or `reopen`, which replace it.
+   */
+  rawFd(): number;
+  /** HANDLE of the port on Windows as a number, with the same caveats as `raw_fd` */
+  rawHandle(): number;
+  /**
+   * Read whatever arrives next, up to `max_len` bytes, within `timeout_ms`.
+   *
+   * Those bytes go to the returned promise instead of `onDataReceived` and
+   * skip any framing; delivery to `onDataReceived` continues afterwards.
+   */
+  readOnce(maxLen: number, timeoutMs: number, abort?: AbortToken | undefined | null): Promise<Buffer>;
+  /**
+   * Write `data` then resolve with the reply to it.
+   *
+   * Transactions are queued with other writes and run one at a time, so the
+   * reply can't be mixed up with another transaction's.
+   */
+  transact(
+    data: Buffer,
+    options?: TransactOptions | undefined | null,
+    abort?: AbortToken | undefined | null,
+  ): Promise<Buffer>;
+  /**
+   * Wait for exclusive use of the port, e.g. to send a command and read several replies with no other task's writes in between.
+   *
+   * Resolves once everything queued before has gone out. Until the lock is released, the write thread only services
+   * writes and transactions made through it; writes queued on the port meanwhile wait their turn.
+   *
+   * While a lock is out, methods that wait for the write thread to carry them out (`setRts`, `drain`, ...) throw
+   * instead of waiting for it.
+   */
+  lock(): Promise<PortLock>;
+  /**
+   * Read exactly `len` bytes within `timeout_ms`, e.g. a binary header of known size.
+   *
+   * Like `read_once` the bytes bypass framing and `onDataReceived`. If they don't all arrive in time the
+   * promise rejects, with the bytes collected so far listed in hex in the error message.
+   */
+  readExact(len: number, timeoutMs: number, abort?: AbortToken | undefined | null): Promise<Buffer>;
+  /**
+   * Read up to `max_len` bytes in `manual_mode`, waiting at most `timeout_ms` for the first of them.
+   *
+   * Blocks the JS thread meanwhile, and returns an empty buffer when nothing arrived in time.
+   */
+  read(maxLen: number, timeoutMs: number): Buffer;
+  /** Write `data` in `manual_mode`, blocking the JS thread until the OS has taken all of it */
+  writeSync(data: Buffer): void;
+  /** Read back the line settings currently in effect on the OS handle */
+  currentSettings(): PortSettings;
+  /** Snapshot everything that can be read back from the OS handle, for debugging a port's setup */
+  describe(): PortDescription;
+  /**
+   * Throw away the partial frame buffered so far, e.g. to resynchronize after a corrupt frame.
+   *
+   * Framing starts over with the next byte read.
+   */
+  resetFrameBuffer(): void;
+  /**
+   * Switch to another framing without reopening, e.g. for a device that goes from text commands to binary streaming.
+   *
+   * Replaces whatever framing the port was opened with, until it is reopened. A partial frame received under
+   * the old framing is dropped and reported through `on_error`. Writes queued before the switch keep the old encoding.
+   * If the switch fails, the old framing and encoding both stay in effect.
+   */
+  setFraming(framing: Framing): void;
+  /**
+   * Wait for the next frame, the same data `on_data_received` is handed.
+   *
+   * Frames are queued from the first call on, up to 256 of them while nothing
+   * awaits them, counting the ones dropped past that in `dropped_callbacks`.
+   * Resolves with null once the port is closed, so it can back an async iterator.
+   */
+  nextFrame(): Promise<Buffer | string | TimestampedData | null>;
+  /**
+   * Stop reading from the port until `resume` is called.
+   *
+   * Incoming bytes are left in the OS input buffer rather than discarded, so
+   * they are delivered after `resume` unless that buffer overflows meanwhile.
+   * On a `shared` port this pauses the device, i.e. every handle on it.
+   */
+  pause(): void;
+  /** Resume reading from the port after `pause`, for every handle on a `shared` port */
+  resume(): void;
+  /** Close the port once every write queued so far has been transmitted */
   close(): void;
+  /**
+   * Like `close`, but stops transmitting queued writes after `timeoutMs`.
+   *
+   * Returns how many bytes were dropped because they hadn't gone out in time. On a `shared` port only
+   * the handle that closes the device last sets a deadline, closing any other handle drops nothing.
+   */
+  closeWithTimeout(timeoutMs: number): number;
+  /** Like `close`, but waits for the worker threads off the JS thread */
+  closeAsync(): Promise<void>;
+  /** Close the port if it is still open, then open it again with the original path, settings and callbacks */
+  reopen(): void;
+  /**
+   * Replace `on_data_received` without closing, e.g. when a device leaves its bootloader for the application protocol.
+   *
+   * The read thread switches between two frames, so every frame goes to exactly one of the two callbacks.
+   */
+  setDataCallback(onDataReceived: (arg: Buffer | string | TimestampedData) => void): void;
+  /**
+   * Report how far writes spanning several `write_chunk_size` chunks have got, e.g. for a firmware upload progress bar.
+   *
+   * Called after a chunk went out at most every 100 ms, and once more when the last one did. Pass null to stop.
+   */
+  setWriteProgressCallback(onWriteProgress: ((progress: WriteProgress) => void) | null): void;
+  /**
+   * Register another callback that receives every frame `onDataReceived` does.
+   *
+   * Returns an id to pass to `remove_data_listener`.
+   */
+  addDataListener(listener: (arg: Buffer | string | TimestampedData) => void): number;
+  /** Unregister a listener added with `add_data_listener`, returns false if the id is unknown */
+  removeDataListener(id: number): boolean;
+  /**
+   * Whether the port can currently be used.
+   *
+   * False after `close`, once a worker thread died on an error, and while the device is disconnected.
+   */
+  isOpen(): boolean;
+  /** Path the port was opened with, or the path of the other end for `open_pty_pair` */
+  get path(): string;
+  /** The `tag` from the settings the port was opened with */
+  get tag(): string | null;
+  /** Traffic counters accumulated since the port was opened, for the whole device on a `shared` port */
+  stats(): PortStats;
+}
+
+/**
+ * Exclusive use of a port's write queue, handed out by `OpenPort.lock`
+ *
+ * While it is held the write thread only services what is sent through the lock, everything queued on the port
+ * waits. Released by `unlock`, or once the lock is garbage collected.
+ */
+export declare class PortLock {
+  /** Queue a write that goes out while the lock is held */
+  write(data: Buffer): void;
+  /** Like `OpenPort.transact`, but serviced while the lock is held */
+  transact(
+    data: Buffer,
+    options?: TransactOptions | undefined | null,
+    abort?: AbortToken | undefined | null,
+  ): Promise<Buffer>;
+  /** Hand the port back once everything sent through the lock went out. Does nothing if already unlocked */
+  unlock(): void;
+  get locked(): boolean;
+}
+
+/** Handle returned by `watch_ports`, keeps the watcher running until `stop` is called */
+export declare class PortWatcher {
+  /** Stop watching, no more events are delivered once this returns */
+  stop(): void;
+}
+
+/** Which directions a port is opened for */
+export declare const enum AccessMode {
+  /** only read, `write` and friends fail */
+  Read = 'Read',
+  /** only write, nothing is read and `on_data_received` is never called */
+  Write = 'Write',
+  ReadWrite = 'ReadWrite',
+}
+
+/**
+ * How many port handles are currently open, e.g. to catch handles a long-running service forgets to close.
+ *
+ * Counts every handle from opening until `close` or garbage collection, including each handle on a shared device.
+ */
+export declare function activePortCount(): number;
+
+/** What happens when `on_data_received` or a data listener throws */
+export declare const enum CallbackErrors {
+  /** leave it uncaught, which ends the process unless an `uncaughtException` handler is installed */
+  Throw = 'Throw',
+  /** swallow it and carry on with the next frame */
+  Ignore = 'Ignore',
+  /** hand it to `on_error` as a `CallbackThrew` error and carry on */
+  Report = 'Report',
+}
+
+/** How the read thread queues calls to `on_data_received` and the data listeners */
+export declare const enum CallbackMode {
+  /** queue every frame, however far behind JS falls */
+  Blocking = 'Blocking',
+  /** drop frames while JS is too far behind, counting them in `dropped_callbacks` */
+  NonBlocking = 'NonBlocking',
 }
 
+/** Which of the platform-gated features work here, so unsupported controls can be hidden */
+export declare function capabilities(): Capabilities;
+
+/** Platform-gated features `capabilities` reports, true where they work */
+export interface Capabilities {
+  /** the `rs485` setting, with RTS toggled by hand where the kernel can't do it */
+  supportsRs485: boolean;
+  /** RS-485 with RTS timed by the kernel, Linux only */
+  supportsKernelRs485: boolean;
+  /** the `low_latency` setting, Linux only */
+  supportsLowLatency: boolean;
+  supportsBreak: boolean;
+  /** reading CTS, DSR, CD and RI and driving RTS and DTR */
+  supportsModemLines: boolean;
+  /** mark and space parity and `write_9bit`, Linux and Windows only */
+  supportsMarkSpaceParity: boolean;
+  supportsOnePointFiveStopBits: boolean;
+  /** the `report_parity_errors` setting, Unix only */
+  supportsParityErrorReporting: boolean;
+  /** the `exclusive` setting, Windows ports are always exclusive */
+  supportsExclusive: boolean;
+  /** the `os_read_buffer_bytes` setting, Windows only */
+  supportsOsReadBufferSize: boolean;
+  /** `open_pty_pair` and `open_loopback`, Unix only */
+  supportsPty: boolean;
+  /** `by_id_path` and `by_path_path` of listed ports, Linux only */
+  supportsByIdPaths: boolean;
+}
+
+/** CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF, not reflected), e.g. 0x29B1 for "123456789" */
+export declare function computeCrc16Ccitt(data: Buffer): number;
+
+/** CRC-32 as used by zlib, Ethernet and PNG, e.g. 0xCBF43926 for "123456789" */
+export declare function computeCrc32(data: Buffer): number;
+
 export declare const enum DataBits {
   Five = 'Five',
   Six = 'Six',
@@ -29,12 +403,213 @@ export declare const enum FlowControl {
   Hardware = 'Hardware',
 }
 
+/** What happens to a partial frame that grows past `max_frame_size` */
+export declare const enum FrameOverflow {
+  /** throw the buffered bytes away */
+  Drop = 'Drop',
+  /** hand them to `on_data_received` in frames of `max_frame_size` bytes */
+  Deliver = 'Deliver',
+}
+
+/** How incoming data is split into frames, packet encodings also apply to writes */
+export type Framing =
+  | { type: 'Raw' }
+  | { type: 'Delimiter', bytes: Buffer, include?: boolean }
+  | { type: 'Delimiters', bytes: Array<Buffer>, include?: boolean }
+  | { type: 'FixedLength', len: number }
+  | { type: 'InterByteTimeout', ms: number }
+  | { type: 'Cobs' }
+  | { type: 'ModbusRtu', gapMs?: number }
+
 export declare function listPorts(): Array<AvailablePort>;
 
+/** List the available ports of one kind, e.g. only USB adapters */
+export declare function listPortsByType(kind: PortKind): Array<AvailablePort>;
+
+/** List the available ports matching `filter`, any USB criteria only match USB ports */
+export declare function listPortsFiltered(filter: PortFilter): Array<AvailablePort>;
+
+/**
+ * Open a port that echoes every write straight back, for testing without hardware.
+ *
+ * Writes go through the usual framing before reaching `on_data_received`.
+ */
+export declare function openLoopback(
+  onDataReceived: (arg: Buffer | string | TimestampedData) => void,
+  onError: (arg: PortError) => void,
+  settings?: PortSettings | undefined | null,
+): OpenPort;
+
+/** Open the USB port whose serial number is `serial`, wherever it is currently attached */
+export declare function openBySerial(
+  serial: string,
+  onDataReceived: (arg: Buffer | string | TimestampedData) => void,
+  onError: (arg: PortError) => void,
+  settings?: PortSettings | undefined | null,
+  onDisconnect?: ((arg: PortError) => void) | undefined | null,
+  onReconnect?: ((arg: string) => void) | undefined | null,
+  onReady?: (() => void) | undefined | null,
+): OpenPort;
+
+/**
+ * Open the port whose `displayName()` is `name`, e.g. the one a user picked from a list.
+ *
+ * Fails rather than guessing when several ports share the name.
+ */
+export declare function openByDisplayName(
+  name: string,
+  onDataReceived: (arg: Buffer | string | TimestampedData) => void,
+  onError: (arg: PortError) => void,
+  settings?: PortSettings | undefined | null,
+  onDisconnect?: ((arg: PortError) => void) | undefined | null,
+  onReconnect?: ((arg: string) => void) | undefined | null,
+  onReady?: (() => void) | undefined | null,
+): OpenPort;
+
+/**
+ * Create a pseudo terminal and open its master end, for testing code that opens a serial port by path.
+ *
+ * The returned port's `path` is the other end, whatever is written to one end can be read from the other.
+ */
+export declare function openPtyPair(
+  onDataReceived: (arg: Buffer | string | TimestampedData) => void,
+  onError: (arg: PortError) => void,
+  settings?: PortSettings | undefined | null,
+): OpenPort;
+
+/** Version of the native addon, e.g. to check a prebuilt binary matches the JS package */
+export declare function version(): string;
+
+/**
+ * Call `on_change` whenever a serial port is added or removed.
+ *
+ * On Linux the events come from udev as they happen. Elsewhere, and where udev isn't available, the port list is
+ * compared every `poll_interval_ms` (defaults to 1000). Ports present when watching starts are not reported.
+ */
+export declare function watchPorts(
+  onChange: (arg: PortChangeEvent) => void,
+  pollIntervalMs?: number | undefined | null,
+): PortWatcher;
+
 export declare const enum Parity {
   None = 'None',
   Odd = 'Odd',
   Even = 'Even',
+  /** the parity bit is always 1, Linux and Windows only */
+  Mark = 'Mark',
+  /** the parity bit is always 0, Linux and Windows only */
+  Space = 'Space',
+}
+
+/** A port that appeared or disappeared since the previous check */
+export interface PortChangeEvent {
+  /** true if the port was plugged in, false if it went away */
+  added: boolean;
+  port: AvailablePort;
+}
+
+/** What a port supports, as far as the OS lets us find out */
+export interface PortCapabilities {
+  portType: string;
+  /** whether the path is a terminal device, unknown on Windows or when the path doesn't exist */
+  isTty?: boolean;
+  /** standard baud rates the driver accepts */
+  baudRates: Array<number>;
+  /** false when `baud_rates` is a generic default list rather than derived from what the driver reports */
+  baudRatesQueried: boolean;
+}
+
+/** Everything that can be read back from an open port's OS handle, null where the platform can't tell */
+export interface PortDescription {
+  path: string;
+  baudRate: number;
+  /** read timeout in ms */
+  timeoutMs: number;
+  dataBits: DataBits;
+  parity: Parity;
+  stopBits: StopBits;
+  flowControl: FlowControl;
+  /** whether other processes are kept from opening the port, null on Windows where ports are always exclusive */
+  exclusive?: boolean;
+  /** whether the driver's low latency mode is on, null where the driver can't be asked (anywhere but Linux) */
+  lowLatency?: boolean;
+  /** bytes waiting in the OS input buffer */
+  bytesToRead?: number;
+  /** bytes waiting in the OS output buffer */
+  bytesToWrite?: number;
+  clearToSend?: boolean;
+  dataSetReady?: boolean;
+  carrierDetect?: boolean;
+  ringIndicator?: boolean;
+}
+
+/** Criteria for `list_ports_filtered`, ports must match every field that is set */
+export interface PortFilter {
+  vid?: number;
+  pid?: number;
+  serial?: string;
+  /** substring the USB manufacturer string must contain */
+  manufacturerContains?: string;
+}
+
+/** How far a write spanning several chunks has got, handed to the write progress callback */
+export interface WriteProgress {
+  /** bytes handed to the OS so far, after any packet encoding */
+  written: number;
+  total: number;
+}
+
+/** A frame together with when it was read, delivered when `timestamp_reads` is set */
+export interface TimestampedData {
+  /** the frame, or its text when `encoding` is set */
+  data: Buffer | string;
+  /** nanoseconds from opening the port until the read that completed the frame returned */
+  timestampNs: number;
+}
+
+/** Error details handed to the `on_error` callback */
+export interface PortError {
+  kind: PortErrorKind;
+  /** raw OS error number when the failure came from the OS */
+  osErrorCode?: number;
+  message: string;
+  /** path of the port the error came from */
+  path?: string;
+  /** the `tag` from the port's settings */
+  tag?: string;
+}
+
+export declare const enum PortErrorKind {
+  /** the device went away, e.g. a USB adapter was unplugged */
+  Disconnected = 'Disconnected',
+  PermissionDenied = 'PermissionDenied',
+  NotFound = 'NotFound',
+  TimedOut = 'TimedOut',
+  /** a partial frame grew past `max_frame_size` and was dropped, or delivered truncated */
+  FrameOverflow = 'FrameOverflow',
+  /** the OS is running the port at a different baud rate than requested */
+  BaudRateMismatch = 'BaudRateMismatch',
+  /** received bytes that couldn't be decoded as a frame and were dropped */
+  InvalidFrame = 'InvalidFrame',
+  /** a byte arrived with a parity or framing error, or a break was received */
+  ParityError = 'ParityError',
+  /** received bytes were lost because the driver's buffer was full, e.g. JS fell too far behind (Linux only) */
+  Overrun = 'Overrun',
+  /** the port couldn't be opened because another application has it open */
+  PortBusy = 'PortBusy',
+  /** a data callback threw, reported when `callback_errors` is `Report` */
+  CallbackThrew = 'CallbackThrew',
+  /** any other I/O failure */
+  Io = 'Io',
+  Other = 'Other',
+}
+
+/** The kinds of port `AvailablePort.type` reports */
+export declare const enum PortKind {
+  Usb = 'Usb',
+  Bluetooth = 'Bluetooth',
+  Pci = 'Pci',
+  Unknown = 'Unknown',
 }
 
 export interface PortSettings {
@@ -45,13 +620,164 @@ export interface PortSettings {
   parity?: Parity;
   stopBits?: StopBits;
   flowControl?: FlowControl;
+  /** size of each read from the port in bytes, between 1 and 1 MiB (defaults to 1024) */
+  readBufferSize?: number;
+  /**
+   * ask the driver for an input buffer of this many bytes, to ride out stalls of the read thread at high baud rates.
+   * Best effort: only Windows lets us ask, and drivers may round or ignore it; elsewhere it is ignored
+   */
+  osReadBufferBytes?: number;
+  /**
+   * have the driver hand over received bytes right away, e.g. instead of after the 16 ms latency timer of FTDI adapters
+   * (defaults to false, Linux only and ignored elsewhere)
+   */
+  lowLatency?: boolean;
+  /** drop everything read for this many ms after opening or reconnecting, e.g. a boot banner */
+  discardMsAfterOpen?: number;
+  /** back off polling a quiet port, waiting up to this many ms between polls once nothing arrives (defaults to no backoff) */
+  maxIdlePollMs?: number;
+  /** label copied into every `PortError` from this port, to tell ports apart in a shared handler */
+  tag?: string;
+  /** stop other processes from opening the port while it is open (defaults to false, ignored on Windows) */
+  exclusive?: boolean;
+  /** level to drive DTR to while opening, e.g. false to keep a board from resetting (defaults to the OS behaviour) */
+  initialDtr?: boolean;
+  /** level to drive RTS to right after opening, before any data flows (defaults to the OS behaviour) */
+  initialRts?: boolean;
+  /** keep reopening the port after the device goes away instead of giving up (defaults to false) */
+  autoReconnect?: boolean;
+  /** how long to wait between reconnect attempts in ms (defaults to 1000) */
+  reconnectIntervalMs?: number;
+  /** treat the device as disconnected if a status query fails, checked this often in ms */
+  livenessPollMs?: number;
+  /** drive RTS around every write for an RS-485 transceiver */
+  rs485?: Rs485Settings;
+  /** drop the echo of our own writes from incoming data, for half-duplex lines that hand it back (defaults to false) */
+  suppressLocalEcho?: boolean;
+  /** how long an echo may take to come back after the write or the previous echoed byte, in ms (defaults to 100) */
+  localEchoWindowMs?: number;
+  /** give up on a write that takes longer than this many ms and move on to the next one */
+  writeTimeoutMs?: number;
+  /** hand writes to the OS at most this many bytes at a time, letting priority writes go out in between (defaults to whole writes) */
+  writeChunkSize?: number;
+  /** gather writes queued within this many ms of each other into one write and flush (defaults to writing each on its own) */
+  writeCoalesceMs?: number;
+  /** most writes to gather into one, sending the batch early once reached (defaults to unlimited) */
+  writeCoalesceCount?: number;
+  /** maximum number of queued writes before `write` starts failing (defaults to unbounded) */
+  writeQueueCapacity?: number;
+  /** hold back reads until at least this many bytes arrived before calling `on_data_received` */
+  minReadBytes?: number;
+  /** deliver held bytes anyway once the oldest has waited this long in ms (defaults to 20) */
+  minReadTimeoutMs?: number;
+  /** how incoming data is split into frames, and for packet encodings how writes are encoded (defaults to raw) */
+  framing?: Framing;
+  /**
+   * deliver data one frame at a time, split on this delimiter
+   *
+   * @deprecated use `framing: { type: 'Delimiter', bytes }`
+   */
+  frameDelimiter?: Buffer;
+  /**
+   * deliver data one frame at a time, split on whichever of these delimiters comes first, e.g. for mixed line endings
+   *
+   * @deprecated use `framing: { type: 'Delimiters', bytes }`
+   */
+  frameDelimiters?: Array<Buffer>;
+  /**
+   * keep the delimiter at the end of each frame (defaults to false)
+   *
+   * @deprecated use the `include` of a `Delimiter` or `Delimiters` framing
+   */
+  includeDelimiter?: boolean;
+  /**
+   * deliver data in frames of exactly this many bytes, cannot be combined with `frame_delimiter`
+   *
+   * @deprecated use `framing: { type: 'FixedLength', len }`
+   */
+  frameLength?: number;
+  /**
+   * end a frame once no byte arrived for this many ms, e.g. the 3.5 character silence of Modbus RTU
+   *
+   * @deprecated use `framing: { type: 'InterByteTimeout', ms }`, or the `gapMs` of a `ModbusRtu` framing
+   */
+  interByteTimeoutMs?: number;
+  /** largest partial frame to buffer before dropping it and reporting an error */
+  maxFrameSize?: number;
+  /** whether a partial frame past `max_frame_size` is dropped or delivered truncated (defaults to dropping it) */
+  frameOverflow?: FrameOverflow;
+  /** deliver every frame as a string decoded with this encoding, split into lines unless other framing is set */
+  encoding?: TextEncoding;
+  /** drop frames that aren't valid text and report an error instead of replacing the bad bytes (defaults to false) */
+  strictDecoding?: boolean;
+  /** whether data callbacks are dropped rather than queued while JS is behind (defaults to blocking) */
+  callbackMode?: CallbackMode;
+  /** what happens when a data callback throws (defaults to leaving it uncaught) */
+  callbackErrors?: CallbackErrors;
+  /** report bytes received with a parity or framing error through `on_error` (defaults to false, Unix only) */
+  reportParityErrors?: boolean;
+  /** append every byte read and written to this file, one timestamped line per read or write */
+  traceFile?: string;
+  /** start a new trace file once it would grow past this many bytes, keeping one previous file (defaults to 10 MiB) */
+  traceFileMaxBytes?: number;
+  /** share the device with other handles opened on the same path with this set, within this process (defaults to false) */
+  shared?: boolean;
+  /** open the device for reading or writing only, skipping the worker thread for the other direction (defaults to both) */
+  accessMode?: AccessMode;
+  /** start no worker threads, data is only read and written by calling `read` and `write_sync` (defaults to false) */
+  manualMode?: boolean;
+  /** hand data callbacks a `TimestampedData` carrying when each frame was read, instead of the bare data (defaults to false) */
+  timestampReads?: boolean;
+}
+
+/** Half-duplex RS-485 with RTS enabling the transmitter */
+export interface Rs485Settings {
+  /** RTS level while transmitting (defaults to true) */
+  rtsOnSend?: boolean;
+  /** RTS level while idle, for transceivers that want the same level both ways (defaults to the opposite of `rts_on_send`) */
+  busIsIdleHigh?: boolean;
+  /** how long to hold RTS after the last byte left the port in microseconds (defaults to 0) */
+  rtsAfterSendDelayUs?: number;
+}
+
+/** Traffic counters for an open port */
+export interface PortStats {
+  bytesRead: number;
+  bytesWritten: number;
+  readErrors: number;
+  writeErrors: number;
+  /** data callbacks dropped in non-blocking mode because JS fell behind */
+  droppedCallbacks: number;
 }
 
 export declare const enum StopBits {
   One = 'One',
+  /** only with five data bits, opening the port with any other number fails as unsupported */
+  OnePointFive = 'OnePointFive',
   Two = 'Two',
 }
 
+/** Text encodings `on_data_received` can decode frames with */
+export declare const enum TextEncoding {
+  Utf8 = 'Utf8',
+  /** 7-bit ASCII, bytes above 0x7F are invalid */
+  Ascii = 'Ascii',
+  /** ISO-8859-1, every byte maps to the code point of the same value */
+  Latin1 = 'Latin1',
+}
+
+/** When `OpenPort::transact` considers the reply complete, it always stops at `timeout_ms` */
+export interface TransactOptions {
+  /** the reply ends with this delimiter, which is kept */
+  delimiter?: Buffer;
+  /** the reply is exactly this many bytes */
+  length?: number;
+  /** give up waiting and resolve with what arrived so far after this many ms (defaults to 1000) */
+  timeoutMs?: number;
+  /** the reply is complete once no byte arrived for this many ms after the first one */
+  idleCompleteMs?: number;
+}
+
 export interface UsbInfo {
   readonly vid: number;
   readonly pid: number;
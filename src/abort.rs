@@ -0,0 +1,74 @@
+// Lets JS cancel a pending `read_once` or `transact`
+
+use napi_derive::napi;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crossbeam::channel::Sender;
+
+use crate::types::Command;
+
+/// Cancels the `read_once` or `transact` it is passed to, e.g. from an `AbortSignal` listener
+#[napi]
+#[derive(Default)]
+pub struct AbortToken {
+  inner: Arc<Abort>,
+}
+
+#[napi]
+impl AbortToken {
+  #[napi(constructor)]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Reject the request this token was passed to with a cancellation error
+  #[napi]
+  pub fn abort(&self) {
+    self.inner.abort();
+  }
+
+  #[napi(getter)]
+  pub fn aborted(&self) -> bool {
+    self.inner.is_aborted()
+  }
+}
+
+impl AbortToken {
+  pub(crate) fn handle(&self) -> Arc<Abort> {
+    self.inner.clone()
+  }
+}
+
+// The part of a token the worker threads hold on to
+#[derive(Default)]
+pub(crate) struct Abort {
+  aborted: AtomicBool,
+  // where to cancel the read once the request reached the read thread
+  target: Mutex<Option<(Sender<Command>, u64)>>,
+}
+
+impl Abort {
+  fn abort(&self) {
+    self.aborted.store(true, Ordering::SeqCst);
+    if let Some((tx, id)) = self.target.lock().unwrap().take() {
+      let _ = tx.send(Command::CancelRead(id));
+    }
+  }
+
+  pub fn is_aborted(&self) -> bool {
+    self.aborted.load(Ordering::SeqCst)
+  }
+
+  // Route a later `abort` to the read with this id, returns false if it was already aborted
+  pub fn attach(&self, tx: &Sender<Command>, id: u64) -> bool {
+    let mut target = self.target.lock().unwrap();
+    if self.is_aborted() {
+      return false;
+    }
+
+    *target = Some((tx.clone(), id));
+    true
+  }
+}
@@ -0,0 +1,30 @@
+// What this build supports on the platform it runs on, for feature detection from JS
+
+use napi_derive::napi;
+
+use crate::types::Capabilities;
+
+/// Which of the platform-gated features work here, so unsupported controls can be hidden
+#[napi]
+pub fn capabilities() -> Capabilities {
+  Capabilities {
+    supports_rs485: true,
+    supports_kernel_rs485: cfg!(target_os = "linux"),
+    supports_low_latency: cfg!(target_os = "linux"),
+    supports_break: true,
+    supports_modem_lines: true,
+    supports_mark_space_parity: cfg!(any(target_os = "linux", windows)),
+    supports_one_point_five_stop_bits: true,
+    supports_parity_error_reporting: cfg!(unix),
+    supports_exclusive: cfg!(unix),
+    supports_os_read_buffer_size: cfg!(windows),
+    supports_pty: cfg!(unix),
+    supports_by_id_paths: cfg!(target_os = "linux"),
+  }
+}
+
+/// Version of the native addon, e.g. to check a prebuilt binary matches the JS package
+#[napi]
+pub fn version() -> String {
+  env!("CARGO_PKG_VERSION").to_string()
+}
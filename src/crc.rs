@@ -0,0 +1,63 @@
+// Checksums used by the built-in framings, also exported for JS
+
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+
+/// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF, not reflected), e.g. 0x29B1 for "123456789"
+#[napi]
+pub fn compute_crc16_ccitt(data: Buffer) -> u16 {
+  crc16_ccitt(&data)
+}
+
+/// CRC-32 as used by zlib, Ethernet and PNG, e.g. 0xCBF43926 for "123456789"
+#[napi]
+pub fn compute_crc32(data: Buffer) -> u32 {
+  crc32(&data)
+}
+
+// CRC-16/MODBUS, sent low byte first
+pub(crate) fn crc16_modbus(data: &[u8]) -> u16 {
+  let mut crc: u16 = 0xFFFF;
+  for &byte in data {
+    crc ^= byte as u16;
+    for _ in 0..8 {
+      crc = if crc & 1 != 0 {
+        (crc >> 1) ^ 0xA001
+      } else {
+        crc >> 1
+      };
+    }
+  }
+  crc
+}
+
+fn crc16_ccitt(data: &[u8]) -> u16 {
+  let mut crc: u16 = 0xFFFF;
+  for &byte in data {
+    crc ^= (byte as u16) << 8;
+    for _ in 0..8 {
+      crc = if crc & 0x8000 != 0 {
+        (crc << 1) ^ 0x1021
+      } else {
+        crc << 1
+      };
+    }
+  }
+  crc
+}
+
+// Reflected, poly 0xEDB88320, with the input and output inverted
+fn crc32(data: &[u8]) -> u32 {
+  let mut crc: u32 = 0xFFFF_FFFF;
+  for &byte in data {
+    crc ^= byte as u32;
+    for _ in 0..8 {
+      crc = if crc & 1 != 0 {
+        (crc >> 1) ^ 0xEDB8_8320
+      } else {
+        crc >> 1
+      };
+    }
+  }
+  !crc
+}
@@ -0,0 +1,60 @@
+// Drops the copy of our own writes a half-duplex line hands back to the receiver
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Shared by both worker threads: the write thread records what it sends, the read thread strips it off again
+pub(crate) struct LocalEcho {
+  // how long an echo may take to come back, counted from the write or the last echoed byte
+  window: Duration,
+  pending: Mutex<Pending>,
+}
+
+#[derive(Default)]
+struct Pending {
+  bytes: VecDeque<u8>,
+  until: Option<Instant>,
+}
+
+impl LocalEcho {
+  pub fn new(window: Duration) -> Self {
+    LocalEcho {
+      window,
+      pending: Mutex::new(Pending::default()),
+    }
+  }
+
+  // Expect `data` to come back, called before it is handed to the OS so a quick echo can't beat it
+  pub fn record(&self, data: &[u8]) {
+    let mut pending = self.pending.lock().unwrap();
+    pending.bytes.extend(data);
+    pending.until = Some(Instant::now() + self.window);
+  }
+
+  // How many leading bytes of `data` are the echo of our writes
+  pub fn strip(&self, data: &[u8]) -> usize {
+    let mut pending = self.pending.lock().unwrap();
+    let now = Instant::now();
+    if pending.until.is_some_and(|until| now >= until) {
+      *pending = Pending::default();
+    }
+
+    let matched = data
+      .iter()
+      .zip(pending.bytes.iter())
+      .take_while(|(received, sent)| received == sent)
+      .count();
+    pending.bytes.drain(..matched);
+
+    if matched < data.len() && !pending.bytes.is_empty() {
+      // something else got in the way, whatever echo was still expected is lost in it
+      *pending = Pending::default();
+    } else if matched > 0 {
+      // long writes echo for as long as they take to transmit
+      pending.until = Some(now + self.window);
+    }
+
+    matched
+  }
+}
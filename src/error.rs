@@ -0,0 +1,297 @@
+// Builds the structured `PortError` handed to JS from the errors we run into
+
+use napi::bindgen_prelude::{JsObjectValue, JsValue};
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
+use napi::Env;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::open_port::{OnErrorCallback, OnReconnectCallback};
+use crate::types::{PortError, PortErrorKind};
+
+impl PortError {
+  pub(crate) fn new(kind: PortErrorKind, message: impl Into<String>) -> Self {
+    PortError {
+      kind,
+      os_error_code: None,
+      message: message.into(),
+      path: None,
+      tag: None,
+    }
+  }
+
+  pub(crate) fn from_io(context: &str, e: &std::io::Error) -> Self {
+    PortError {
+      kind: io_error_kind(e),
+      os_error_code: e.raw_os_error(),
+      message: format!("{context}: {e}"),
+      path: None,
+      tag: None,
+    }
+  }
+
+  // `serialport` keeps no OS error code of its own, only the message of an `io::Error` it wrapped still carries one
+  pub(crate) fn from_serialport(context: &str, e: &serialport::Error) -> Self {
+    let kind = match e.kind() {
+      serialport::ErrorKind::NoDevice => PortErrorKind::NotFound,
+      serialport::ErrorKind::Io(kind) => io_error_kind(&kind.into()),
+      _ => PortErrorKind::Other,
+    };
+
+    PortError {
+      os_error_code: wrapped_os_error_code(e),
+      ..PortError::new(kind, format!("{context}: {e}"))
+    }
+  }
+}
+
+// The code `std` appends as "(os error N)" to the message of an OS error
+fn wrapped_os_error_code(e: &serialport::Error) -> Option<i32> {
+  let (_, code) = e.description.strip_suffix(')')?.rsplit_once("(os error ")?;
+  code.parse().ok()
+}
+
+// Hands errors to JS, labelled with the port they came from.
+// Every handle on a shared device registers its own callbacks, each of them hears about everything.
+pub(crate) struct ErrorSink {
+  pub path: String,
+  next_id: AtomicU32,
+  handlers: Mutex<Vec<(u32, ErrorHandlers)>>,
+}
+
+// The callbacks and tag one handle was opened with
+pub(crate) struct ErrorHandlers {
+  pub on_error: Arc<OnErrorCallback>,
+  pub on_disconnect: Option<Arc<OnErrorCallback>>,
+  pub on_reconnect: Option<Arc<OnReconnectCallback>>,
+  pub tag: Option<String>,
+}
+
+impl ErrorSink {
+  pub fn new(path: String) -> Self {
+    ErrorSink {
+      path,
+      next_id: AtomicU32::new(0),
+      handlers: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn add(&self, handlers: ErrorHandlers) -> u32 {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    self.handlers.lock().unwrap().push((id, handlers));
+    id
+  }
+
+  pub fn remove(&self, id: u32) {
+    self
+      .handlers
+      .lock()
+      .unwrap()
+      .retain(|(handler, _)| *handler != id);
+  }
+
+  fn label(&self, err: &PortError, handlers: &ErrorHandlers) -> PortError {
+    PortError {
+      path: Some(self.path.clone()),
+      tag: handlers.tag.clone(),
+      ..err.clone()
+    }
+  }
+
+  pub fn report(&self, err: PortError) {
+    for (_, handlers) in self.handlers.lock().unwrap().iter() {
+      let _ = handlers.on_error.call(
+        self.label(&err, handlers),
+        ThreadsafeFunctionCallMode::NonBlocking,
+      );
+    }
+  }
+
+  // Handles without an `on_disconnect` get it through `on_error`
+  pub fn disconnected(&self, err: PortError) {
+    for (_, handlers) in self.handlers.lock().unwrap().iter() {
+      let on_disconnect = handlers
+        .on_disconnect
+        .as_ref()
+        .unwrap_or(&handlers.on_error);
+      let _ = on_disconnect.call(
+        self.label(&err, handlers),
+        ThreadsafeFunctionCallMode::NonBlocking,
+      );
+    }
+  }
+
+  pub fn reconnected(&self, path: &str) {
+    for (_, handlers) in self.handlers.lock().unwrap().iter() {
+      if let Some(on_reconnect) = &handlers.on_reconnect {
+        let _ = on_reconnect.call(path.to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+      }
+    }
+  }
+}
+
+// What becomes of an error thrown by a data callback, as `callback_errors` asks
+#[derive(Clone)]
+pub(crate) enum Thrown {
+  // handed back to napi, which leaves it uncaught
+  Rethrow,
+  Ignore,
+  Report(Arc<ErrorSink>),
+}
+
+impl Thrown {
+  // Takes what a data callback returned, an error left in the result is raised in JS
+  pub fn handle(&self, ret: napi::Result<()>) -> napi::Result<()> {
+    let Err(e) = ret else {
+      return Ok(());
+    };
+
+    match self {
+      Thrown::Rethrow => Err(e),
+      Thrown::Ignore => Ok(()),
+      Thrown::Report(errors) => {
+        errors.report(PortError::new(
+          PortErrorKind::CallbackThrew,
+          format!("a data callback threw: {}", e.reason),
+        ));
+        Ok(())
+      }
+    }
+  }
+}
+
+// The message leads with the kind, which `coded` turns into the thrown error's `code` once back on the JS thread
+impl From<PortError> for napi::Error {
+  fn from(e: PortError) -> Self {
+    let kind = match e.kind {
+      PortErrorKind::Disconnected => "Disconnected",
+      PortErrorKind::PermissionDenied => "PermissionDenied",
+      PortErrorKind::NotFound => "NotFound",
+      PortErrorKind::TimedOut => "TimedOut",
+      PortErrorKind::FrameOverflow => "FrameOverflow",
+      PortErrorKind::BaudRateMismatch => "BaudRateMismatch",
+      PortErrorKind::InvalidFrame => "InvalidFrame",
+      PortErrorKind::ParityError => "ParityError",
+      PortErrorKind::Overrun => "Overrun",
+      PortErrorKind::PortBusy => "PortBusy",
+      PortErrorKind::CallbackThrew => "CallbackThrew",
+      PortErrorKind::Io => "Io",
+      PortErrorKind::Other => "Other",
+    };
+
+    match e.os_error_code {
+      Some(code) => napi::Error::from_reason(format!("[{kind}, os error {code}] {}", e.message)),
+      None => napi::Error::from_reason(format!("[{kind}] {}", e.message)),
+    }
+  }
+}
+
+// napi only gives a thrown error one of its own statuses as `code`. An error made from a `PortError` is rebuilt
+// as a JS error with its kind as `code` and the OS error code, if any, as `errno`. Anything else is left alone.
+pub(crate) fn coded(env: &Env, e: napi::Error) -> napi::Error {
+  let Some((kind, os_error_code)) = tagged(&e.reason) else {
+    return e;
+  };
+
+  let rebuild = || -> napi::Result<napi::Error> {
+    let mut error = env.create_error(napi::Error::from_reason(e.reason.as_str()))?;
+    error.set_named_property("code", kind)?;
+    if let Some(code) = os_error_code {
+      error.set_named_property("errno", code)?;
+    }
+    Ok(napi::Error::from(error.to_unknown()))
+  };
+  rebuild().unwrap_or(e)
+}
+
+// The kind and OS error code `From<PortError>` put in front of the message
+fn tagged(reason: &str) -> Option<(&str, Option<i32>)> {
+  let (tag, _) = reason.strip_prefix('[')?.split_once("] ")?;
+  let (kind, os_error_code) = match tag.split_once(", os error ") {
+    Some((kind, code)) => (kind, Some(code.parse().ok()?)),
+    None => (tag, None),
+  };
+  // nothing but a kind name fits in there
+  let named = !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic());
+  named.then_some((kind, os_error_code))
+}
+
+pub(crate) trait Coded<T> {
+  // `coded` for whatever error the result holds, at the boundary of a function that throws to JS
+  fn coded(self, env: &Env) -> napi::Result<T>;
+}
+
+impl<T> Coded<T> for napi::Result<T> {
+  fn coded(self, env: &Env) -> napi::Result<T> {
+    self.map_err(|e| coded(env, e))
+  }
+}
+
+fn io_error_kind(e: &std::io::Error) -> PortErrorKind {
+  if is_disconnect(e) {
+    return PortErrorKind::Disconnected;
+  }
+
+  match e.kind() {
+    std::io::ErrorKind::PermissionDenied => PortErrorKind::PermissionDenied,
+    std::io::ErrorKind::NotFound => PortErrorKind::NotFound,
+    std::io::ErrorKind::TimedOut => PortErrorKind::TimedOut,
+    _ => PortErrorKind::Io,
+  }
+}
+
+// Whether an error means the device itself went away
+pub(crate) fn is_disconnect(e: &std::io::Error) -> bool {
+  if matches!(
+    e.kind(),
+    std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::UnexpectedEof
+  ) {
+    return true;
+  }
+
+  e.raw_os_error().is_some_and(is_disconnect_code)
+}
+
+// Unplugging a USB adapter surfaces as one of these on Unix
+#[cfg(unix)]
+fn is_disconnect_code(code: i32) -> bool {
+  matches!(code, libc::ENXIO | libc::ENODEV | libc::EIO)
+}
+
+// ERROR_BAD_COMMAND, ERROR_GEN_FAILURE and ERROR_DEVICE_NOT_CONNECTED
+#[cfg(windows)]
+fn is_disconnect_code(code: i32) -> bool {
+  matches!(code, 22 | 31 | 1167)
+}
+
+// Whether opening failed because someone else holds the port. `serialport` files that under `NoDevice`: EBUSY from
+// TIOCEXCL and a lock another process holds on Unix, where a missing device is `NotFound` instead. Windows files a
+// missing COM port there too, so only one that is still listed is busy, with the "access denied" of an open one.
+pub(crate) fn is_busy(e: &serialport::Error, path: &str) -> bool {
+  match e.kind() {
+    serialport::ErrorKind::Io(std::io::ErrorKind::ResourceBusy) => true,
+    serialport::ErrorKind::NoDevice if cfg!(windows) => serialport::available_ports()
+      .is_ok_and(|ports| ports.iter().any(|port| port.port_name == path)),
+    serialport::ErrorKind::NoDevice => true,
+    _ => false,
+  }
+}
+
+// The same for a port opened one way, which fails with the OS error itself
+pub(crate) fn is_busy_io(e: &std::io::Error) -> bool {
+  e.kind() == std::io::ErrorKind::ResourceBusy || e.raw_os_error().is_some_and(is_busy_code)
+}
+
+// EBUSY from TIOCEXCL, EWOULDBLOCK from a lock another process holds
+#[cfg(unix)]
+fn is_busy_code(code: i32) -> bool {
+  code == libc::EBUSY || code == libc::EWOULDBLOCK
+}
+
+// ERROR_ACCESS_DENIED and ERROR_SHARING_VIOLATION, COM ports are exclusive and one that is
+// already open is reported as either
+#[cfg(windows)]
+fn is_busy_code(code: i32) -> bool {
+  matches!(code, 5 | 32)
+}
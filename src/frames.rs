@@ -0,0 +1,97 @@
+// Frames queued for `OpenPort.next_frame`, handed to the promises awaiting them straight from the read thread
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use napi::{Env, JsDeferred};
+
+use crate::open_port::Data;
+
+type FrameResolver = Box<dyn FnOnce(Env) -> napi::Result<Option<Data>> + Send>;
+pub(crate) type FrameWaiter = JsDeferred<Option<Data>, FrameResolver>;
+
+// Shared by a handle and the read thread, closed by whichever of them is done first
+pub(crate) struct FrameQueue {
+  capacity: usize,
+  frames: Mutex<Frames>,
+}
+
+#[derive(Default)]
+struct Frames {
+  queued: VecDeque<Data>,
+  // `next_frame` calls waiting for a frame, there are only ever any while nothing is queued
+  waiting: VecDeque<FrameWaiter>,
+  closed: bool,
+}
+
+// What became of a frame handed to the queue
+pub(crate) enum Pushed {
+  Queued,
+  // the queue is full, nobody has been awaiting `next_frame` for a while
+  Dropped,
+  Closed,
+}
+
+impl FrameQueue {
+  pub fn new(capacity: usize) -> Self {
+    FrameQueue {
+      capacity,
+      frames: Mutex::new(Frames::default()),
+    }
+  }
+
+  // Settle `waiter` with the next frame, right away if one is queued or the queue is closed
+  pub fn pop(&self, waiter: FrameWaiter) {
+    let mut frames = self.frames.lock().unwrap();
+    match frames.queued.pop_front() {
+      Some(data) => settle(waiter, Some(data)),
+      None if frames.closed => settle(waiter, None),
+      None => frames.waiting.push_back(waiter),
+    }
+  }
+
+  fn push(&self, data: Data) -> Pushed {
+    let mut frames = self.frames.lock().unwrap();
+    if frames.closed {
+      return Pushed::Closed;
+    }
+
+    if let Some(waiter) = frames.waiting.pop_front() {
+      settle(waiter, Some(data));
+    } else if frames.queued.len() < self.capacity {
+      frames.queued.push_back(data);
+    } else {
+      return Pushed::Dropped;
+    }
+
+    Pushed::Queued
+  }
+
+  // No more frames come in and everyone still waiting gets null, frames already queued are still handed out
+  pub fn close(&self) {
+    let mut frames = self.frames.lock().unwrap();
+    frames.closed = true;
+    for waiter in frames.waiting.drain(..) {
+      settle(waiter, None);
+    }
+  }
+}
+
+fn settle(waiter: FrameWaiter, data: Option<Data>) {
+  waiter.resolve(Box::new(move |_| Ok(data)));
+}
+
+// The read thread's end of a queue, which closes it once the read thread is gone
+pub(crate) struct FrameSender(pub Arc<FrameQueue>);
+
+impl FrameSender {
+  pub fn send(&self, data: Data) -> Pushed {
+    self.0.push(data)
+  }
+}
+
+impl Drop for FrameSender {
+  fn drop(&mut self) {
+    self.0.close();
+  }
+}
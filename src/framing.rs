@@ -0,0 +1,563 @@
+// Splits the raw byte stream coming off the port into frames for `on_data_received`
+
+use std::time::Duration;
+
+use crate::crc::crc16_modbus;
+use crate::types::{FrameOverflow, Framing, PortSettings, TextEncoding};
+
+pub(crate) enum FrameMode {
+  // deliver every read as-is
+  Raw,
+  // deliver everything up to one of the delimiters, sorted longest first, optionally keeping the delimiter
+  Delimiter {
+    delimiters: Vec<Vec<u8>>,
+    include: bool,
+  },
+  // deliver exactly this many bytes at a time
+  FixedLength(usize),
+  // decode COBS packets, each ending in a zero byte
+  Cobs,
+  // a pause in the incoming data this long ends the frame, which may have to pass a Modbus CRC check
+  Gap {
+    gap: Duration,
+    modbus: bool,
+  },
+}
+
+impl FrameMode {
+  // The accumulation strategy for `framing`, the only settings consulted are the ones it refines: the Modbus
+  // silence and the line splitting of text
+  fn from_framing(framing: &Framing, settings: &PortSettings) -> napi::Result<Self> {
+    Ok(match framing {
+      Framing::Raw if settings.encoding.is_some() => FrameMode::Delimiter {
+        delimiters: vec![b"\n".to_vec()],
+        include: settings.include_delimiter.unwrap_or(false),
+      },
+      Framing::Raw => FrameMode::Raw,
+      Framing::Delimiter { bytes, .. } if bytes.is_empty() => {
+        return Err(napi::Error::from_reason(
+          "the framing delimiter must not be empty",
+        ));
+      }
+      Framing::Delimiter { bytes, include } => FrameMode::Delimiter {
+        delimiters: vec![bytes.to_vec()],
+        include: include.unwrap_or(false),
+      },
+      Framing::Delimiters { bytes, .. }
+        if bytes.is_empty() || bytes.iter().any(|d| d.is_empty()) =>
+      {
+        return Err(napi::Error::from_reason(
+          "the framing delimiters must hold at least one delimiter and none may be empty",
+        ));
+      }
+      Framing::Delimiters { bytes, include } => FrameMode::delimiters(
+        bytes.iter().map(|d| d.to_vec()).collect(),
+        include.unwrap_or(false),
+      ),
+      Framing::FixedLength { len: 0 } => {
+        return Err(napi::Error::from_reason(
+          "the framing length must be greater than 0",
+        ));
+      }
+      Framing::FixedLength { len } => FrameMode::FixedLength(*len as usize),
+      Framing::InterByteTimeout { ms: 0 } => {
+        return Err(napi::Error::from_reason(
+          "the framing timeout must be greater than 0",
+        ));
+      }
+      Framing::InterByteTimeout { ms } => FrameMode::Gap {
+        gap: Duration::from_millis(*ms as u64),
+        modbus: false,
+      },
+      Framing::Cobs => FrameMode::Cobs,
+      Framing::ModbusRtu { gap_ms } => FrameMode::Gap {
+        gap: match gap_ms.or(settings.inter_byte_timeout_ms) {
+          Some(0) => {
+            return Err(napi::Error::from_reason(
+              "the Modbus RTU gap must be greater than 0",
+            ));
+          }
+          Some(ms) => Duration::from_millis(ms as u64),
+          None => modbus_silence(settings.baud_rate.unwrap_or(115_200)),
+        },
+        modbus: true,
+      },
+    })
+  }
+
+  fn delimiters(mut delimiters: Vec<Vec<u8>>, include: bool) -> Self {
+    // where several match at the same spot the longest wins, e.g. \r\n over \r
+    delimiters.sort_by_key(|d| std::cmp::Reverse(d.len()));
+    FrameMode::Delimiter {
+      delimiters,
+      include,
+    }
+  }
+}
+
+// Encodings applied to outgoing data
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Encoding {
+  Cobs,
+  ModbusRtu,
+}
+
+impl Encoding {
+  pub fn from_settings(settings: &PortSettings) -> Option<Self> {
+    settings.framing.as_ref().and_then(Encoding::from_framing)
+  }
+
+  pub fn from_framing(framing: &Framing) -> Option<Self> {
+    match framing {
+      Framing::Cobs => Some(Encoding::Cobs),
+      Framing::ModbusRtu { .. } => Some(Encoding::ModbusRtu),
+      Framing::Raw
+      | Framing::Delimiter { .. }
+      | Framing::Delimiters { .. }
+      | Framing::FixedLength { .. }
+      | Framing::InterByteTimeout { .. } => None,
+    }
+  }
+
+  pub fn encode(self, data: &[u8]) -> Vec<u8> {
+    match self {
+      Encoding::Cobs => cobs_encode(data),
+      Encoding::ModbusRtu => {
+        let mut frame = Vec::with_capacity(data.len() + 2);
+        frame.extend_from_slice(data);
+        frame.extend_from_slice(&crc16_modbus(data).to_le_bytes());
+        frame
+      }
+    }
+  }
+}
+
+pub(crate) enum FrameEvent {
+  Frame(Vec<u8>),
+  // the accumulator grew past the maximum frame size, and was either dropped or handed out in pieces
+  Overflow { len: usize, delivered: bool },
+  // a complete frame failed to decode and was dropped
+  Invalid(usize),
+}
+
+pub(crate) struct Framer {
+  mode: FrameMode,
+  max_frame_size: Option<usize>,
+  // hand out an overflowing accumulator as truncated frames instead of dropping it
+  deliver_overflow: bool,
+  // bytes received but not yet part of a complete frame
+  buf: Vec<u8>,
+  // the delimiter that ended the last frame right at the end of a read, when a longer one starts with it
+  cut: Option<usize>,
+}
+
+impl Framer {
+  pub fn new(mode: FrameMode, max_frame_size: Option<usize>, deliver_overflow: bool) -> Self {
+    Framer {
+      mode,
+      max_frame_size,
+      deliver_overflow,
+      buf: Vec::new(),
+      cut: None,
+    }
+  }
+
+  // Build the framer described by the user supplied settings
+  pub fn from_settings(settings: &PortSettings) -> napi::Result<Self> {
+    let mode = match &settings.framing {
+      Some(framing) => {
+        // the separate settings are shorthands for `framing`, Modbus RTU just takes its gap from them
+        let shorthands = settings.frame_delimiter.is_some()
+          || settings.frame_delimiters.is_some()
+          || settings.frame_length.is_some()
+          || (settings.inter_byte_timeout_ms.is_some()
+            && !matches!(framing, Framing::ModbusRtu { .. }));
+        if shorthands {
+          return Err(napi::Error::from_reason(
+            "framing can't be combined with frameDelimiter, frameDelimiters, frameLength or interByteTimeoutMs",
+          ));
+        }
+        FrameMode::from_framing(framing, settings)?
+      }
+      None => Self::shorthand_mode(settings)?,
+    };
+
+    let max_frame_size = match settings.max_frame_size {
+      Some(0) => {
+        return Err(napi::Error::from_reason(
+          "maxFrameSize must be greater than 0",
+        ))
+      }
+      Some(max) => Some(max as usize),
+      None => None,
+    };
+
+    let deliver_overflow = matches!(settings.frame_overflow, Some(FrameOverflow::Deliver));
+
+    Ok(Framer::new(mode, max_frame_size, deliver_overflow))
+  }
+
+  // The mode picked by the separate framing settings, when `framing` itself isn't set
+  fn shorthand_mode(settings: &PortSettings) -> napi::Result<FrameMode> {
+    let delimiters = match (&settings.frame_delimiter, &settings.frame_delimiters) {
+      (Some(_), Some(_)) => {
+        return Err(napi::Error::from_reason(
+          "only one of frameDelimiter and frameDelimiters can be used",
+        ));
+      }
+      (Some(delimiter), None) if delimiter.is_empty() => {
+        return Err(napi::Error::from_reason("frameDelimiter must not be empty"));
+      }
+      (None, Some(delimiters))
+        if delimiters.is_empty() || delimiters.iter().any(|d| d.is_empty()) =>
+      {
+        return Err(napi::Error::from_reason(
+          "frameDelimiters must hold at least one delimiter and none may be empty",
+        ));
+      }
+      (Some(delimiter), None) => Some(vec![delimiter.to_vec()]),
+      (None, Some(delimiters)) => Some(delimiters.iter().map(|d| d.to_vec()).collect::<Vec<_>>()),
+      (None, None) => None,
+    };
+
+    let modes = [
+      delimiters.is_some(),
+      settings.frame_length.is_some(),
+      settings.inter_byte_timeout_ms.is_some(),
+    ];
+    if modes.iter().filter(|&&set| set).count() > 1 {
+      return Err(napi::Error::from_reason(
+        "only one of frameDelimiter, frameLength and interByteTimeoutMs can be used",
+      ));
+    }
+
+    if let Some(delimiters) = delimiters {
+      return Ok(FrameMode::delimiters(
+        delimiters,
+        settings.include_delimiter.unwrap_or(false),
+      ));
+    }
+
+    let framing = match (settings.frame_length, settings.inter_byte_timeout_ms) {
+      (Some(0), _) => {
+        return Err(napi::Error::from_reason(
+          "frameLength must be greater than 0",
+        ));
+      }
+      (Some(len), _) => Framing::FixedLength { len },
+      (_, Some(0)) => {
+        return Err(napi::Error::from_reason(
+          "interByteTimeoutMs must be greater than 0",
+        ));
+      }
+      (_, Some(ms)) => Framing::InterByteTimeout { ms },
+      (None, None) => Framing::Raw,
+    };
+    FrameMode::from_framing(&framing, settings)
+  }
+
+  // The framer `set_framing` switches to: only `framing`, ignoring the shorthand settings,
+  // but keeping the size limit and text line splitting the port was opened with
+  pub fn for_framing(framing: &Framing, settings: &PortSettings) -> napi::Result<Self> {
+    Ok(Framer::new(
+      FrameMode::from_framing(framing, settings)?,
+      settings.max_frame_size.map(|max| max as usize),
+      matches!(settings.frame_overflow, Some(FrameOverflow::Deliver)),
+    ))
+  }
+
+  // Bytes held towards the next frame
+  pub fn buffered(&self) -> usize {
+    self.buf.len()
+  }
+
+  // Drop any partially received frame
+  pub fn clear(&mut self) {
+    self.buf.clear();
+    self.cut = None;
+  }
+
+  // Whether reads are delivered untouched
+  pub fn is_raw(&self) -> bool {
+    matches!(self.mode, FrameMode::Raw)
+  }
+
+  // How long the line has to stay quiet before `flush` should be called
+  pub fn gap(&self) -> Option<Duration> {
+    match self.mode {
+      FrameMode::Gap { gap, .. } => Some(gap),
+      _ => None,
+    }
+  }
+
+  // Hand out whatever has accumulated as a frame of its own
+  pub fn flush(&mut self) -> Option<FrameEvent> {
+    if self.buf.is_empty() {
+      return None;
+    }
+
+    let frame = std::mem::take(&mut self.buf);
+    if !matches!(self.mode, FrameMode::Gap { modbus: true, .. }) {
+      return Some(FrameEvent::Frame(frame));
+    }
+
+    // address, function code and the CRC at the very least
+    if frame.len() < 4 {
+      return Some(FrameEvent::Invalid(frame.len()));
+    }
+
+    let (payload, crc) = frame.split_at(frame.len() - 2);
+    if crc16_modbus(payload).to_le_bytes() != crc {
+      return Some(FrameEvent::Invalid(frame.len()));
+    }
+
+    Some(FrameEvent::Frame(payload.to_vec()))
+  }
+
+  // Feed freshly read bytes, returning any frames they completed
+  pub fn push(&mut self, data: &[u8]) -> Vec<FrameEvent> {
+    let mut events = Vec::new();
+
+    match &self.mode {
+      FrameMode::Raw => events.push(FrameEvent::Frame(data.to_vec())),
+      FrameMode::Delimiter {
+        delimiters,
+        include,
+      } => {
+        // only rescan the tail that could hold a delimiter split across reads
+        let mut start = self.buf.len().saturating_sub(delimiters[0].len() - 1);
+        self.buf.extend_from_slice(data);
+
+        // the last frame ended on e.g. \r, drop the \n of a \r\n instead of making it an empty frame
+        if let Some(cut) = self.cut.take().map(|i| &delimiters[i]) {
+          let rest = delimiters
+            .iter()
+            .filter(|d| d.len() > cut.len() && d.starts_with(cut))
+            .map(|d| &d[cut.len()..])
+            .find(|rest| self.buf.starts_with(rest));
+          if let Some(rest) = rest {
+            self.buf.drain(..rest.len());
+          }
+        }
+
+        while let Some((pos, i)) = find_any(&self.buf[start..], delimiters) {
+          let delimiter = &delimiters[i];
+          let end = start + pos + delimiter.len();
+          let mut frame: Vec<u8> = self.buf.drain(..end).collect();
+          if !include {
+            frame.truncate(frame.len() - delimiter.len());
+          }
+          events.push(FrameEvent::Frame(frame));
+          start = 0;
+
+          if self.buf.is_empty()
+            && delimiters[..i]
+              .iter()
+              .any(|longer| longer.starts_with(delimiter))
+          {
+            self.cut = Some(i);
+          }
+        }
+      }
+      FrameMode::Cobs => {
+        let mut start = self.buf.len();
+        self.buf.extend_from_slice(data);
+
+        while let Some(pos) = self.buf[start..].iter().position(|&b| b == 0) {
+          let end = start + pos;
+          let frame: Vec<u8> = self.buf.drain(..=end).collect();
+          let encoded = &frame[..frame.len() - 1];
+          // a lone delimiter carries no packet, senders use it to resync
+          if !encoded.is_empty() {
+            match cobs_decode(encoded) {
+              Some(decoded) => events.push(FrameEvent::Frame(decoded)),
+              None => events.push(FrameEvent::Invalid(encoded.len())),
+            }
+          }
+          start = 0;
+        }
+      }
+      // frames end on silence, which `flush` takes care of
+      FrameMode::Gap { .. } => self.buf.extend_from_slice(data),
+      FrameMode::FixedLength(len) => {
+        self.buf.extend_from_slice(data);
+        while self.buf.len() >= *len {
+          events.push(FrameEvent::Frame(self.buf.drain(..*len).collect()));
+        }
+      }
+    }
+
+    if let Some(max) = self.max_frame_size {
+      if self.buf.len() > max {
+        events.push(FrameEvent::Overflow {
+          len: self.buf.len(),
+          delivered: self.deliver_overflow,
+        });
+        if self.deliver_overflow {
+          // whatever is left over starts the next frame
+          while self.buf.len() > max {
+            events.push(FrameEvent::Frame(self.buf.drain(..max).collect()));
+          }
+        } else {
+          self.buf.clear();
+        }
+      }
+    }
+
+    events
+  }
+}
+
+#[derive(Clone, Copy)]
+enum Charset {
+  Utf8,
+  Ascii,
+  Latin1,
+}
+
+// Turns frames into strings for `on_data_received`
+pub(crate) struct TextDecoder {
+  charset: Charset,
+  strict: bool,
+  // drop the \r of \r\n line endings, which only makes sense with the default line framing
+  trim_cr: bool,
+}
+
+impl TextDecoder {
+  pub fn from_settings(settings: &PortSettings) -> Option<Self> {
+    let charset = match settings.encoding.as_ref()? {
+      TextEncoding::Utf8 => Charset::Utf8,
+      TextEncoding::Ascii => Charset::Ascii,
+      TextEncoding::Latin1 => Charset::Latin1,
+    };
+    let line_framing = settings.frame_delimiter.is_none()
+      && settings.frame_delimiters.is_none()
+      && settings.frame_length.is_none()
+      && settings.inter_byte_timeout_ms.is_none()
+      && matches!(settings.framing, None | Some(Framing::Raw));
+
+    Some(TextDecoder {
+      charset,
+      strict: settings.strict_decoding.unwrap_or(false),
+      trim_cr: line_framing && !settings.include_delimiter.unwrap_or(false),
+    })
+  }
+
+  // None if the frame isn't valid text and decoding is strict
+  pub fn decode(&self, mut frame: Vec<u8>) -> Option<String> {
+    if self.trim_cr && frame.last() == Some(&b'\r') {
+      frame.pop();
+    }
+
+    match self.charset {
+      Charset::Utf8 if self.strict => String::from_utf8(frame).ok(),
+      Charset::Utf8 => Some(match String::from_utf8(frame) {
+        Ok(text) => text,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+      }),
+      Charset::Ascii if self.strict && !frame.is_ascii() => None,
+      Charset::Ascii => Some(
+        frame
+          .iter()
+          .map(|&b| {
+            if b.is_ascii() {
+              b as char
+            } else {
+              char::REPLACEMENT_CHARACTER
+            }
+          })
+          .collect(),
+      ),
+      Charset::Latin1 => Some(frame.iter().map(|&b| b as char).collect()),
+    }
+  }
+}
+
+// Encode text for writing, failing with the first character the encoding can't represent
+pub(crate) fn encode_text(text: String, encoding: &TextEncoding) -> Result<Vec<u8>, char> {
+  let limit = match encoding {
+    TextEncoding::Utf8 => return Ok(text.into_bytes()),
+    TextEncoding::Ascii => 0x7F,
+    TextEncoding::Latin1 => 0xFF,
+  };
+
+  text
+    .chars()
+    .map(|c| {
+      if c as u32 <= limit {
+        Ok(c as u8)
+      } else {
+        Err(c)
+      }
+    })
+    .collect()
+}
+
+// Position of the earliest match of any of `needles` and which one it is, the first listed wins a tie
+fn find_any(haystack: &[u8], needles: &[Vec<u8>]) -> Option<(usize, usize)> {
+  (0..haystack.len()).find_map(|pos| {
+    needles
+      .iter()
+      .position(|needle| haystack[pos..].starts_with(needle))
+      .map(|i| (pos, i))
+  })
+}
+
+// The 3.5 character silence that separates Modbus RTU frames, fixed above 19200 baud by the spec
+fn modbus_silence(baud: u32) -> Duration {
+  if baud > 19_200 {
+    return Duration::from_micros(1750);
+  }
+
+  // 11 bits per character
+  Duration::from_micros(38_500_000 / baud.max(1) as u64)
+}
+
+// COBS encode `data` and terminate it with the zero delimiter
+fn cobs_encode(data: &[u8]) -> Vec<u8> {
+  let mut out = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+  // index of the code byte for the block being written
+  let mut code_pos = 0;
+  out.push(0);
+
+  for &byte in data {
+    if byte != 0 {
+      out.push(byte);
+    }
+
+    let len = out.len() - code_pos;
+    // a block ends at a zero, or after 254 data bytes
+    if byte == 0 || len == 0xFF {
+      out[code_pos] = len as u8;
+      code_pos = out.len();
+      out.push(0);
+    }
+  }
+
+  out[code_pos] = (out.len() - code_pos) as u8;
+  out.push(0);
+  out
+}
+
+// Decode a single COBS packet without its delimiter, None if it is corrupt
+fn cobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+  let mut out = Vec::with_capacity(data.len());
+  let mut i = 0;
+
+  while i < data.len() {
+    let code = data[i] as usize;
+    if code == 0 || i + code > data.len() {
+      return None;
+    }
+
+    out.extend_from_slice(&data[i + 1..i + code]);
+    i += code;
+
+    // every block but a full one stands for a zero, except the last
+    if code < 0xFF && i < data.len() {
+      out.push(0);
+    }
+  }
+
+  Some(out)
+}
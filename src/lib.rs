@@ -1,10 +1,34 @@
 // Library entry: re-export modules and public items
 
+pub mod abort;
+pub mod capabilities;
+pub mod crc;
+mod echo;
+mod error;
+mod frames;
+mod framing;
+pub mod lock;
 pub mod open_port;
+mod platform;
 pub mod ports;
+mod reader;
+mod trace;
 pub mod types;
+pub mod watcher;
+mod writer;
 
-pub use open_port::OpenPort;
-pub use ports::list_ports;
+pub use abort::AbortToken;
+pub use capabilities::{capabilities, version};
+pub use crc::{compute_crc16_ccitt, compute_crc32};
+pub use lock::PortLock;
+pub use open_port::{active_port_count, OpenPort};
 pub use ports::AvailablePort;
-pub use types::{DataBits, FlowControl, Parity, PortSettings, StopBits};
+pub use ports::{
+  list_ports, list_ports_by_type, list_ports_filtered, open_by_display_name, open_by_serial,
+  open_loopback, open_pty_pair,
+};
+pub use types::{
+  DataBits, FlowControl, Framing, Parity, PortError, PortErrorKind, PortKind, PortSettings,
+  StopBits,
+};
+pub use watcher::{watch_ports, PortWatcher};
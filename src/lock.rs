@@ -0,0 +1,144 @@
+// Lets one JS task have the write queue to itself for a multi-step exchange
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::{Env, JsDeferred};
+use napi_derive::napi;
+
+use crossbeam::channel::{bounded, Sender};
+
+use crate::abort::AbortToken;
+use crate::open_port::{transact_until, ReplyTask, SharedState};
+use crate::types::{Command, TransactOptions, TransactRequest};
+
+/// Exclusive use of a port's write queue, handed out by `OpenPort.lock`
+///
+/// While it is held the write thread only services what is sent through the lock, everything queued on the port
+/// waits. Released by `unlock`, or once the lock is garbage collected.
+#[napi]
+pub struct PortLock {
+  tx: Option<LockTx>,
+  readable: bool,
+}
+
+#[napi]
+impl PortLock {
+  /// Queue a write that goes out while the lock is held
+  #[napi]
+  pub fn write(&self, data: Buffer) -> napi::Result<()> {
+    self.send(Command::Write(data, None))
+  }
+
+  /// Like `OpenPort.transact`, but serviced while the lock is held
+  #[napi(ts_return_type = "Promise<Buffer>")]
+  pub fn transact(
+    &self,
+    data: Buffer,
+    options: Option<TransactOptions>,
+    abort: Option<&AbortToken>,
+  ) -> napi::Result<AsyncTask<ReplyTask<Buffer>>> {
+    if !self.readable {
+      return Err(napi::Error::from_reason("port was opened write-only"));
+    }
+
+    let (until, timeout) = transact_until(options)?;
+    let (reply_tx, reply_rx) = bounded(1);
+    self.send(Command::Transact(TransactRequest {
+      data,
+      until,
+      timeout,
+      reply: reply_tx,
+      abort: abort.map(AbortToken::handle),
+    }))?;
+
+    Ok(AsyncTask::new(ReplyTask { rx: reply_rx }))
+  }
+
+  /// Hand the port back once everything sent through the lock went out. Does nothing if already unlocked
+  #[napi]
+  pub fn unlock(&mut self) {
+    self.tx.take();
+  }
+
+  #[napi(getter)]
+  pub fn locked(&self) -> bool {
+    self.tx.is_some()
+  }
+}
+
+impl PortLock {
+  fn send(&self, cmd: Command) -> napi::Result<()> {
+    let Some(tx) = &self.tx else {
+      return Err(napi::Error::from_reason("port was unlocked"));
+    };
+
+    tx.tx
+      .send(cmd)
+      .map_err(|_| napi::Error::from_reason("port is closed"))
+  }
+}
+
+// The sending half of a lock, counted in `SharedState::locks` for as long as it is around
+pub(crate) struct LockTx {
+  tx: Sender<Command>,
+  state: Arc<SharedState>,
+}
+
+impl LockTx {
+  pub fn new(tx: Sender<Command>, state: Arc<SharedState>) -> Self {
+    state.locks.fetch_add(1, Ordering::Relaxed);
+    LockTx { tx, state }
+  }
+}
+
+impl Drop for LockTx {
+  fn drop(&mut self) {
+    self.state.locks.fetch_sub(1, Ordering::Relaxed);
+  }
+}
+
+type LockResolver = Box<dyn FnOnce(Env) -> napi::Result<PortLock> + Send>;
+
+// Settles the promise `OpenPort.lock` returned once the write thread got through everything queued before the lock.
+// Resolved from the write thread, so waiting for a lock doesn't tie up a libuv thread.
+pub(crate) struct LockGrant {
+  deferred: Option<JsDeferred<PortLock, LockResolver>>,
+  lock: Option<PortLock>,
+}
+
+impl LockGrant {
+  pub fn new(deferred: JsDeferred<PortLock, LockResolver>, tx: LockTx, readable: bool) -> Self {
+    LockGrant {
+      deferred: Some(deferred),
+      lock: Some(PortLock {
+        tx: Some(tx),
+        readable,
+      }),
+    }
+  }
+
+  pub fn grant(mut self) {
+    if let (Some(deferred), Some(lock)) = (self.deferred.take(), self.lock.take()) {
+      deferred.resolve(Box::new(move |_| Ok(lock)));
+    }
+  }
+
+  pub fn reject(mut self, reason: &str) {
+    if let Some(deferred) = self.deferred.take() {
+      deferred.reject(napi::Error::from_reason(reason));
+    }
+  }
+}
+
+// the write thread gave up on the lock, e.g. because the port closed first
+impl Drop for LockGrant {
+  fn drop(&mut self) {
+    if let Some(deferred) = self.deferred.take() {
+      deferred.reject(napi::Error::from_reason(
+        "port closed before the lock was granted",
+      ));
+    }
+  }
+}
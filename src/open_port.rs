@@ -1,127 +1,1860 @@
-use napi::bindgen_prelude::Buffer;
+use napi::bindgen_prelude::{AsyncTask, Buffer, Either3, Object, ToNapiValue, TypeName};
 use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Env, Task};
 use napi_derive::napi;
 
+use serialport::SerialPort;
+
 use std::io::{Read, Write};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{bounded, unbounded, Receiver, Sender, TrySendError};
+
+use crate::abort::AbortToken;
+use crate::echo::LocalEcho;
+use crate::error::{coded, is_busy, is_busy_io, Coded, ErrorHandlers, ErrorSink, Thrown};
+use crate::frames::{FrameQueue, FrameSender};
+use crate::framing::{encode_text, Encoding, Framer, TextDecoder};
+use crate::lock::{LockGrant, LockTx};
+use crate::platform::{
+  bus_is_idle_high, enable_kernel_rs485, enable_low_latency, enable_one_point_five_stop_bits,
+  enable_parity_marking, enable_stick_parity, has_low_latency, has_one_point_five_stop_bits,
+  open_one_way, overrun_count, raw_handle, set_input_buffer_size, stick_parity,
+};
+use crate::reader::{Coalesce, ParityMarks, Reader, Reconnect, UsbIdentity};
+use crate::trace::Trace;
+use crate::types::{
+  AccessMode, CallbackErrors, CallbackMode, Command, ControlLine, DataBits, FlowControl, Framing,
+  LockRequest, Parity, PortDescription, PortError, PortErrorKind, PortSettings, PortStats,
+  ReadRequest, ReadUntil, Reply, StopBits, TextEncoding, TimestampedData, TransactOptions,
+  TransactRequest, WriteProgress,
+};
+use crate::writer::{ManualRs485, WriteCoalesce, Writer};
+
+// What `on_data_received` is handed: the raw frame, its text when `encoding` is set, or either of them with the
+// time it was read when `timestamp_reads` is set
+pub type Data = Either3<Buffer, String, TimestampedData>;
+
+pub type OnDataReceivedCallback = ThreadsafeFunction<Data, (), Data, napi::Status, false>;
+pub type OnErrorCallback = ThreadsafeFunction<PortError, (), PortError, napi::Status, false>;
+pub type OnReconnectCallback = ThreadsafeFunction<String, (), String, napi::Status, false>;
+pub type OnReadyCallback = ThreadsafeFunction<(), (), (), napi::Status, false>;
+pub type OnWriteProgressCallback =
+  ThreadsafeFunction<WriteProgress, (), WriteProgress, napi::Status, false>;
+
+// How far the effective baud rate may drift from the requested one before we warn about it
+const BAUD_RATE_TOLERANCE_PERCENT: u64 = 2;
+
+// How often `wait_for_cts` checks the line
+const CTS_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Frames `next_frame` holds on to while nothing awaits it, later ones are dropped
+const FRAME_QUEUE_CAPACITY: usize = 256;
+
+// How long a garbage collected handle keeps transmitting what was still queued
+const DROP_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Handles opened and not closed yet, for `active_port_count`
+static ACTIVE_PORTS: AtomicU32 = AtomicU32::new(0);
+
+/// How many port handles are currently open, e.g. to catch handles a long-running service forgets to close.
+///
+/// Counts every handle from opening until `close` or garbage collection, including each handle on a shared device.
+#[napi]
+pub fn active_port_count() -> u32 {
+  ACTIVE_PORTS.load(Ordering::Relaxed)
+}
+
+// Upper bound for `PortSettings::read_buffer_size`
+const MAX_READ_BUFFER_SIZE: u32 = 1024 * 1024;
+
+// The platform specific port type returned by `open_native`
+#[cfg(unix)]
+pub(crate) type NativePort = serialport::TTYPort;
+#[cfg(windows)]
+pub(crate) type NativePort = serialport::COMPort;
+
+#[napi]
+pub struct OpenPort {
+  // thread handles, wrapped in an option so we can join them without having to take self without reference
+  read_thread: Option<thread::JoinHandle<()>>,
+  write_thread: Option<thread::JoinHandle<()>>,
+  // sender for writes and anything that must be ordered with them
+  write_tx: Sender<Command>,
+  // sender for writes that go out ahead of `write_tx`
+  priority_tx: Sender<Command>,
+  // sender for commands serviced by the read thread
+  cmd_tx: Sender<Command>,
+  // sender is wrapped in an option so we can drop it without having to take self without reference
+  kill_tx: Option<Sender<()>>,
+  // state shared with the worker threads
+  state: Arc<SharedState>,
+  // how the port was opened, for `reopen`
+  origin: Arc<Origin>,
+  // our handle on the far end of a pseudo terminal pair, which keeps it alive for whoever opens it next
+  pty_slave: Option<NativePort>,
+  // which directions `access_mode` allows
+  readable: bool,
+  writable: bool,
+  // the device this handle shares with others, in shared mode
+  shared: Option<Arc<SharedPort>>,
+  // the listener this handle's `on_data_received` was registered as, in shared mode
+  listener: Option<u32>,
+  // where the worker threads report errors
+  errors: Arc<ErrorSink>,
+  // the callbacks this handle registered with `errors`, in shared mode
+  handlers: Option<u32>,
+  // frames queued for `next_frame`, subscribed on its first call
+  frames: OnceLock<Arc<FrameQueue>>,
+  // the handle itself in manual mode, where no worker thread owns it
+  manual: Option<Mutex<NativePort>>,
+}
+
+// Flags and counters the worker threads and the JS side both look at
+#[derive(Default)]
+pub(crate) struct SharedState {
+  // when set the read thread stops reading, leaving incoming bytes in the OS buffer
+  pub paused: AtomicBool,
+  // set by the read thread while the device is gone
+  pub disconnected: AtomicBool,
+  // cleared as soon as either worker thread exits, for whatever reason
+  pub running: AtomicBool,
+  pub stats: Counters,
+  pub listeners: Listeners,
+  // locks handed out or waiting to be granted, the write thread services nothing else meanwhile
+  pub locks: AtomicU32,
+  // how long the write thread may keep draining after `close_with_timeout`
+  pub close_deadline: Mutex<Option<Instant>>,
+  // bytes given up on because the close timeout ran out
+  pub dropped_on_close: AtomicU64,
+  // non-blocking data callbacks queued for JS that haven't run yet
+  pub pending_callbacks: AtomicUsize,
+  // the packet encoding of writes queued from now on, `set_framing` may have changed it since opening
+  pub encoding: Mutex<Option<Encoding>>,
+}
+
+// Traffic counters, only ever touched with relaxed atomics
+#[derive(Default)]
+pub(crate) struct Counters {
+  pub bytes_read: AtomicU64,
+  pub bytes_written: AtomicU64,
+  pub read_errors: AtomicU64,
+  pub write_errors: AtomicU64,
+  pub dropped_callbacks: AtomicU64,
+}
+
+impl Counters {
+  pub fn add_written(&self, len: usize) {
+    self.bytes_written.fetch_add(len as u64, Ordering::Relaxed);
+  }
+
+  fn snapshot(&self) -> PortStats {
+    PortStats {
+      bytes_read: self.bytes_read.load(Ordering::Relaxed) as i64,
+      bytes_written: self.bytes_written.load(Ordering::Relaxed) as i64,
+      read_errors: self.read_errors.load(Ordering::Relaxed) as i64,
+      write_errors: self.write_errors.load(Ordering::Relaxed) as i64,
+      dropped_callbacks: self.dropped_callbacks.load(Ordering::Relaxed) as i64,
+    }
+  }
+
+  fn reset(&self) {
+    self.bytes_read.store(0, Ordering::Relaxed);
+    self.bytes_written.store(0, Ordering::Relaxed);
+    self.read_errors.store(0, Ordering::Relaxed);
+    self.write_errors.store(0, Ordering::Relaxed);
+    self.dropped_callbacks.store(0, Ordering::Relaxed);
+  }
+}
+
+type ListenerList = Arc<Vec<(u32, Arc<OnDataReceivedCallback>)>>;
+
+// Extra data listeners registered after opening.
+// The list is replaced rather than mutated, so the read thread only holds the
+// lock long enough to clone the `Arc` and never while calling into JS.
+#[derive(Default)]
+pub(crate) struct Listeners {
+  next_id: AtomicU32,
+  list: Mutex<ListenerList>,
+}
+
+impl Listeners {
+  pub fn snapshot(&self) -> ListenerList {
+    self.list.lock().unwrap().clone()
+  }
+
+  fn add(&self, callback: Arc<OnDataReceivedCallback>) -> u32 {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+    let mut list = self.list.lock().unwrap();
+    let mut updated = list.as_ref().clone();
+    updated.push((id, callback));
+    *list = Arc::new(updated);
+    id
+  }
+
+  // Frames are delivered to a snapshot of the list, so each goes to either the old or the new callback
+  fn replace(&self, id: u32, callback: Arc<OnDataReceivedCallback>) {
+    let mut list = self.list.lock().unwrap();
+    *list = Arc::new(
+      list
+        .iter()
+        .map(|(listener, old)| {
+          let callback = if *listener == id { &callback } else { old };
+          (*listener, callback.clone())
+        })
+        .collect(),
+    );
+  }
+
+  fn remove(&self, id: u32) -> bool {
+    let mut list = self.list.lock().unwrap();
+    if !list.iter().any(|(listener, _)| *listener == id) {
+      return false;
+    }
+    *list = Arc::new(
+      list
+        .iter()
+        .filter(|(listener, _)| *listener != id)
+        .cloned()
+        .collect(),
+    );
+    true
+  }
+}
+
+#[napi]
+impl OpenPort {
+  #[napi]
+  pub fn write(&self, data: Buffer) -> napi::Result<()> {
+    self.enqueue(Command::Write(data, None))
+  }
+
+  /// Encode `text` and queue it like `write`.
+  ///
+  /// Uses `encoding`, or the port's `encoding` setting, falling back to UTF-8. Fails if the text
+  /// contains a character the encoding can't represent.
+  #[napi]
+  pub fn write_string(&self, text: String, encoding: Option<TextEncoding>) -> napi::Result<()> {
+    let encoding = encoding
+      .as_ref()
+      .or(self.origin.settings.encoding.as_ref())
+      .unwrap_or(&TextEncoding::Utf8);
+    let data = encode_text(text, encoding).map_err(|c| {
+      let name = match encoding {
+        TextEncoding::Utf8 => "UTF-8",
+        TextEncoding::Ascii => "ASCII",
+        TextEncoding::Latin1 => "Latin-1",
+      };
+      napi::Error::from_reason(format!("{c:?} can't be encoded as {name}"))
+    })?;
+
+    self.write(data.into())
+  }
+
+  /// Queue a write ahead of every regular write still waiting to go out, e.g. an abort byte.
+  ///
+  /// Priority writes go out in the order they were queued, and never count against `write_queue_capacity`.
+  #[napi]
+  pub fn write_priority(&self, data: Buffer) -> napi::Result<()> {
+    self.ensure_writable()?;
+    self
+      .priority_tx
+      .send(Command::Write(data, None))
+      .map_err(|e| napi::Error::from_reason(format!("failed to send write to thread: {e}")))
+  }
+
+  /// Queue several writes at once, they go out back to back in order.
+  ///
+  /// Cheaper than calling `write` for each chunk, and takes a single slot in a bounded write queue.
+  #[napi]
+  pub fn write_many(&self, chunks: Vec<Buffer>) -> napi::Result<()> {
+    self.enqueue(Command::WriteBatch(chunks))
+  }
+
+  /// Queue a write that goes out one byte at a time, waiting `byte_delay_us` microseconds after each byte
+  /// has left the port, for receivers too slow to take data at full speed.
+  ///
+  /// Each pause is a sleep on the write thread, which at microsecond resolution costs CPU time and
+  /// usually overshoots by tens of microseconds.
+  #[napi]
+  pub fn write_slow(&self, data: Buffer, byte_delay_us: u32) -> napi::Result<()> {
+    self.enqueue(Command::WriteSlow(
+      data,
+      Duration::from_micros(byte_delay_us as u64),
+    ))
+  }
+
+  /// Queue 9-bit words for a multidrop bus, sending the 9th bit as the parity bit.
+  ///
+  /// A word goes out with the 9th bit set when it has any bit of `address_mask` set (defaults to 0x100),
+  /// the low 8 bits are the data. Needs a port opened with mark or space parity, so Linux or Windows only.
+  #[napi(js_name = "write9Bit")]
+  pub fn write_9bit(&self, data: Vec<u16>, address_mask: Option<u16>) -> napi::Result<()> {
+    let mask = address_mask.unwrap_or(0x100);
+    if !matches!(
+      self.origin.settings.parity,
+      Some(Parity::Mark | Parity::Space)
+    ) {
+      return Err(napi::Error::from_reason(
+        "9-bit writes need the port opened with Mark or Space parity",
+      ));
+    }
+    if self.state.encoding.lock().unwrap().is_some() {
+      return Err(napi::Error::from_reason(
+        "9-bit writes can't be combined with framing",
+      ));
+    }
+    if let Some(word) = data.iter().find(|&&word| word & !(0xFF | mask) != 0) {
+      return Err(napi::Error::from_reason(format!(
+        "{word:#x} has bits set outside the data byte and addressMask"
+      )));
+    }
+
+    self.enqueue(Command::Write9Bit(data, mask))
+  }
+
+  /// Whether `write` would fail right now because the bounded write queue is full
+  #[napi]
+  pub fn write_would_block(&self) -> bool {
+    self.write_tx.is_full()
+  }
+
+  /// How many entries the write thread still has to work through, priority writes included.
+  ///
+  /// Each `write`, `write_many` batch, transaction and queued line change counts once.
+  #[napi]
+  pub fn pending_writes(&self) -> u32 {
+    if !self.writable {
+      return 0;
+    }
+
+    (self.write_tx.len() + self.priority_tx.len()) as u32
+  }
+
+  /// Queue a write, waiting off the JS thread for room if the write queue is full
+  #[napi(ts_return_type = "Promise<void>")]
+  pub fn write_async(&self, data: Buffer) -> napi::Result<AsyncTask<EnqueueTask>> {
+    self.ensure_writable()?;
+    Ok(AsyncTask::new(EnqueueTask {
+      tx: self.write_tx.clone(),
+      cmd: Some(Command::Write(data, None)),
+    }))
+  }
+
+  /// Queue a write and resolve once it has been handed to the OS.
+  ///
+  /// Write failures reject the returned promise instead of going to `onError`.
+  #[napi(ts_return_type = "Promise<void>")]
+  pub fn write_and_wait(&self, data: Buffer) -> napi::Result<AsyncTask<ReplyTask<()>>> {
+    self.ensure_writable()?;
+    self.request_async(&self.write_tx, |reply| Command::Write(data, Some(reply)))
+  }
+
+  /// Change how long each read waits for data before the read thread checks for other work.
+  ///
+  /// 0 makes reads return immediately, so the read thread polls back to back unless `max_idle_poll_ms` is set.
+  #[napi]
+  pub fn set_timeout(&self, timeout_ms: u32) -> napi::Result<()> {
+    let timeout = Duration::from_millis(timeout_ms as u64);
+    self.request(&self.cmd_tx, |reply| Command::SetTimeout(timeout, reply))
+  }
+
+  /// Change the baud rate of the open port without reopening it.
+  ///
+  /// The read and write handles are clones of the same OS handle, so the
+  /// change applies to both directions.
+  #[napi]
+  pub fn set_baud_rate(&self, baud: u32) -> napi::Result<()> {
+    self.request(&self.cmd_tx, |reply| Command::SetBaudRate(baud, reply))
+  }
+
+  /// Change the flow control of the open port without reopening it, e.g. once the peer is known.
+  ///
+  /// Like the baud rate this is a setting of the OS handle both directions share.
+  #[napi]
+  pub fn set_flow_control(&self, flow_control: FlowControl) -> napi::Result<()> {
+    let flow_control = flow_control.into();
+    self.request(&self.cmd_tx, |reply| {
+      Command::SetFlowControl(flow_control, reply)
+    })
+  }
+
+  /// Read the state of the Clear To Send line
+  #[napi]
+  pub fn read_clear_to_send(&self) -> napi::Result<bool> {
+    self.request(&self.cmd_tx, |reply| {
+      Command::ReadControlLine(ControlLine::ClearToSend, reply)
+    })
+  }
+
+  /// Wait for the device to assert CTS, resolving false if it didn't within `timeout_ms`.
+  ///
+  /// Resolves true straight away if CTS is already asserted.
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn wait_for_cts(&self, timeout_ms: u32) -> napi::Result<AsyncTask<WaitForCtsTask>> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+    let rx = self.send_request(&self.cmd_tx, Command::CloneHandle)?;
+    Ok(AsyncTask::new(WaitForCtsTask { rx, deadline }))
+  }
+
+  /// Read the state of the Data Set Ready line
+  #[napi]
+  pub fn read_data_set_ready(&self) -> napi::Result<bool> {
+    self.request(&self.cmd_tx, |reply| {
+      Command::ReadControlLine(ControlLine::DataSetReady, reply)
+    })
+  }
+
+  /// Read the state of the Carrier Detect line
+  #[napi]
+  pub fn read_carrier_detect(&self) -> napi::Result<bool> {
+    self.request(&self.cmd_tx, |reply| {
+      Command::ReadControlLine(ControlLine::CarrierDetect, reply)
+    })
+  }
+
+  /// Read the state of the Ring Indicator line
+  #[napi]
+  pub fn read_ring_indicator(&self) -> napi::Result<bool> {
+    self.request(&self.cmd_tx, |reply| {
+      Command::ReadControlLine(ControlLine::RingIndicator, reply)
+    })
+  }
+
+  /// Set the Request To Send line, ordered after any writes already queued
+  #[napi]
+  pub fn set_rts(&self, level: bool) -> napi::Result<()> {
+    self.request(&self.write_tx, |reply| Command::SetRts(level, reply))
+  }
+
+  /// Set the Data Terminal Ready line, ordered after any writes already queued
+  #[napi]
+  pub fn set_dtr(&self, level: bool) -> napi::Result<()> {
+    self.request(&self.write_tx, |reply| Command::SetDtr(level, reply))
+  }
+
+  /// Drop DTR for the given milliseconds and raise it again, e.g. to reset a board, ordered after any writes already queued
+  #[napi]
+  pub fn pulse_dtr(&self, low_ms: u32) -> napi::Result<()> {
+    self.request(&self.write_tx, |reply| Command::PulseDtr(low_ms, reply))
+  }
+
+  /// Assert a break condition for the given milliseconds, ordered after any writes already queued
+  #[napi]
+  pub fn send_break(&self, duration_ms: u32) -> napi::Result<()> {
+    self.request(&self.write_tx, |reply| {
+      Command::SendBreak(duration_ms, reply)
+    })
+  }
+
+  /// Assert or clear the break condition, ordered after any writes already queued
+  #[napi]
+  pub fn set_break(&self, asserted: bool) -> napi::Result<()> {
+    self.request(&self.write_tx, |reply| Command::SetBreak(asserted, reply))
+  }
+
+  /// Discard any bytes received by the OS but not yet read
+  #[napi]
+  pub fn clear_input(&self) -> napi::Result<()> {
+    self.request(&self.cmd_tx, |reply| {
+      Command::Clear(serialport::ClearBuffer::Input, reply)
+    })
+  }
+
+  /// Discard any bytes handed to the OS but not yet transmitted
+  #[napi]
+  pub fn clear_output(&self) -> napi::Result<()> {
+    self.request(&self.cmd_tx, |reply| {
+      Command::Clear(serialport::ClearBuffer::Output, reply)
+    })
+  }
+
+  /// Block until every write queued so far has been transmitted
+  #[napi]
+  pub fn drain(&self) -> napi::Result<()> {
+    self.request(&self.write_tx, Command::Drain)
+  }
+
+  /// Number of bytes received by the OS and waiting to be read
+  #[napi]
+  pub fn bytes_to_read(&self) -> napi::Result<u32> {
+    self.request(&self.cmd_tx, Command::BytesToRead)
+  }
+
+  /// Number of bytes sitting in the OS output buffer.
+  ///
+  /// Writes still queued inside this addon are not included.
+  #[napi]
+  pub fn bytes_to_write(&self) -> napi::Result<u32> {
+    self.request(&self.cmd_tx, Command::BytesToWrite)
+  }
+
+  /// File descriptor of the port on Unix, for ioctls this addon doesn't wrap.
+  ///
+  /// The descriptor stays owned by the read thread: don't close it, don't read from it, and
+  /// don't hold on to it across a reconnect or `reopen`, which replace it.
+  #[napi]
+  pub fn raw_fd(&self) -> napi::Result<i32> {
+    if cfg!(windows) {
+      return Err(napi::Error::from_reason(
+        "rawFd is only available on Unix, use rawHandle on Windows",
+      ));
+    }
+    self
+      .request(&self.cmd_tx, Command::RawHandle)
+      .map(|fd| fd as i32)
+  }
+
+  /// HANDLE of the port on Windows as a number, with the same caveats as `raw_fd`
+  #[napi]
+  pub fn raw_handle(&self) -> napi::Result<i64> {
+    if cfg!(unix) {
+      return Err(napi::Error::from_reason(
+        "rawHandle is only available on Windows, use rawFd on Unix",
+      ));
+    }
+    self.request(&self.cmd_tx, Command::RawHandle)
+  }
+
+  /// Read whatever arrives next, up to `max_len` bytes, within `timeout_ms`.
+  ///
+  /// Those bytes go to the returned promise instead of `onDataReceived` and
+  /// skip any framing; delivery to `onDataReceived` continues afterwards.
+  #[napi(ts_return_type = "Promise<Buffer>")]
+  pub fn read_once(
+    &self,
+    max_len: u32,
+    timeout_ms: u32,
+    abort: Option<&AbortToken>,
+  ) -> napi::Result<AsyncTask<ReplyTask<Buffer>>> {
+    self.ensure_readable()?;
+    if max_len == 0 || max_len > MAX_READ_BUFFER_SIZE {
+      return Err(napi::Error::from_reason(format!(
+        "maxLen must be between 1 and {MAX_READ_BUFFER_SIZE}"
+      )));
+    }
+
+    let id = ReadRequest::next_id();
+    if abort.is_some_and(|abort| !abort.handle().attach(&self.cmd_tx, id)) {
+      return Err(napi::Error::from_reason("read cancelled"));
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+    self.request_async(&self.cmd_tx, |reply| {
+      Command::ReadOnce(ReadRequest {
+        id,
+        until: ReadUntil::AnyData(max_len as usize),
+        deadline,
+        reply,
+        collected: Vec::new(),
+        last_data: None,
+        armed: None,
+      })
+    })
+  }
+
+  /// Write `data` then resolve with the reply to it.
+  ///
+  /// Transactions are queued with other writes and run one at a time, so the
+  /// reply can't be mixed up with another transaction's.
+  #[napi(ts_return_type = "Promise<Buffer>")]
+  pub fn transact(
+    &self,
+    data: Buffer,
+    options: Option<TransactOptions>,
+    abort: Option<&AbortToken>,
+  ) -> napi::Result<AsyncTask<ReplyTask<Buffer>>> {
+    self.ensure_readable()?;
+    self.ensure_writable()?;
+    let (until, timeout) = transact_until(options)?;
+    self.request_async(&self.write_tx, |reply| {
+      Command::Transact(TransactRequest {
+        data,
+        until,
+        timeout,
+        reply,
+        abort: abort.map(AbortToken::handle),
+      })
+    })
+  }
+
+  /// Wait for exclusive use of the port, e.g. to send a command and read several replies with no other task's writes in between.
+  ///
+  /// Resolves once everything queued before has gone out. Until the lock is released, the write thread only services
+  /// writes and transactions made through it; writes queued on the port meanwhile wait their turn.
+  ///
+  /// While a lock is out, methods that wait for the write thread to carry them out (`setRts`, `drain`, ...) throw
+  /// instead of waiting for it.
+  #[napi(ts_return_type = "Promise<PortLock>")]
+  pub fn lock<'env>(&self, env: &'env Env) -> napi::Result<Object<'env>> {
+    self.ensure_writable()?;
+
+    let (tx, rx) = unbounded();
+    let (deferred, promise) = env.create_deferred()?;
+    let request = Command::Lock(LockRequest {
+      rx,
+      grant: LockGrant::new(deferred, LockTx::new(tx, self.state.clone()), self.readable),
+    });
+    // JS already holds the promise, so from here on failures reject it instead of throwing
+    if let Err(e) = self.write_tx.try_send(request) {
+      let reason = match e {
+        TrySendError::Full(_) => "write queue is full",
+        TrySendError::Disconnected(_) => "port is closed",
+      };
+      if let Command::Lock(request) = e.into_inner() {
+        request.grant.reject(reason);
+      }
+    }
 
-use crossbeam::channel::{bounded, unbounded, Receiver, RecvError, Sender};
+    Ok(promise)
+  }
+
+  /// Read exactly `len` bytes within `timeout_ms`, e.g. a binary header of known size.
+  ///
+  /// Like `read_once` the bytes bypass framing and `onDataReceived`. If they don't all arrive in time the
+  /// promise rejects, with the bytes collected so far listed in hex in the error message.
+  #[napi(ts_return_type = "Promise<Buffer>")]
+  pub fn read_exact(
+    &self,
+    len: u32,
+    timeout_ms: u32,
+    abort: Option<&AbortToken>,
+  ) -> napi::Result<AsyncTask<ReplyTask<Buffer>>> {
+    self.ensure_readable()?;
+    if len == 0 || len > MAX_READ_BUFFER_SIZE {
+      return Err(napi::Error::from_reason(format!(
+        "len must be between 1 and {MAX_READ_BUFFER_SIZE}"
+      )));
+    }
+
+    let id = ReadRequest::next_id();
+    if abort.is_some_and(|abort| !abort.handle().attach(&self.cmd_tx, id)) {
+      return Err(napi::Error::from_reason("read cancelled"));
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+    self.request_async(&self.cmd_tx, |reply| {
+      Command::ReadOnce(ReadRequest {
+        id,
+        until: ReadUntil::Exact(len as usize),
+        deadline,
+        reply,
+        collected: Vec::new(),
+        last_data: None,
+        armed: None,
+      })
+    })
+  }
+
+  /// Read up to `max_len` bytes in `manual_mode`, waiting at most `timeout_ms` for the first of them.
+  ///
+  /// Blocks the JS thread meanwhile, and returns an empty buffer when nothing arrived in time.
+  #[napi]
+  pub fn read(&self, env: &Env, max_len: u32, timeout_ms: u32) -> napi::Result<Buffer> {
+    if max_len == 0 || max_len > MAX_READ_BUFFER_SIZE {
+      return Err(napi::Error::from_reason(format!(
+        "maxLen must be between 1 and {MAX_READ_BUFFER_SIZE}"
+      )));
+    }
+
+    let mut port = self.manual_port()?;
+    port
+      .set_timeout(Duration::from_millis(timeout_ms as u64))
+      .map_err(|e| napi::Error::from_reason(format!("failed to set timeout: {e}")))?;
+
+    let mut buf = vec![0u8; max_len as usize];
+    match port.read(&mut buf) {
+      Ok(n) => buf.truncate(n),
+      Err(e) if e.kind() == std::io::ErrorKind::TimedOut => buf.clear(),
+      Err(e) => return Err(coded(env, PortError::from_io("failed to read", &e).into())),
+    }
+    self
+      .state
+      .stats
+      .bytes_read
+      .fetch_add(buf.len() as u64, Ordering::Relaxed);
+
+    Ok(buf.into())
+  }
+
+  /// Write `data` in `manual_mode`, blocking the JS thread until the OS has taken all of it
+  #[napi]
+  pub fn write_sync(&self, env: &Env, data: Buffer) -> napi::Result<()> {
+    let mut port = self.manual_port()?;
+    port
+      .write_all(&data)
+      .map_err(|e| coded(env, PortError::from_io("failed to write", &e).into()))?;
+    self.state.stats.add_written(data.len());
+
+    Ok(())
+  }
+
+  /// Read back the line settings currently in effect on the OS handle
+  #[napi]
+  pub fn current_settings(&self) -> napi::Result<PortSettings> {
+    self.request(&self.cmd_tx, Command::CurrentSettings)
+  }
+
+  /// Snapshot everything that can be read back from the OS handle, for debugging a port's setup
+  #[napi]
+  pub fn describe(&self) -> napi::Result<PortDescription> {
+    let description = self.request(&self.cmd_tx, Command::Describe)?;
+    Ok(PortDescription {
+      path: self.path(),
+      ..description
+    })
+  }
+
+  /// Throw away the partial frame buffered so far, e.g. to resynchronize after a corrupt frame.
+  ///
+  /// Framing starts over with the next byte read.
+  #[napi]
+  pub fn reset_frame_buffer(&self) -> napi::Result<()> {
+    self.request(&self.cmd_tx, Command::ResetFrameBuffer)
+  }
+
+  /// Wait for the next frame, the same data `on_data_received` is handed.
+  ///
+  /// Frames are queued from the first call on, up to 256 of them while nothing
+  /// awaits them, counting the ones dropped past that in `dropped_callbacks`.
+  /// Resolves with null once the port is closed, so it can back an async iterator.
+  #[napi(ts_return_type = "Promise<Buffer | string | TimestampedData | null>")]
+  pub fn next_frame<'env>(&self, env: &'env Env) -> napi::Result<Object<'env>> {
+    if !self.readable {
+      return Err(napi::Error::from_reason("port was opened write-only"));
+    }
+
+    let (deferred, promise) = env.create_deferred()?;
+    let frames = self.frames.get_or_init(|| {
+      let frames = Arc::new(FrameQueue::new(FRAME_QUEUE_CAPACITY));
+      // on a closed port the sender is dropped right away, which closes the queue
+      let _ = self
+        .cmd_tx
+        .send(Command::SubscribeFrames(FrameSender(frames.clone())));
+      frames
+    });
+    frames.pop(deferred);
+
+    Ok(promise)
+  }
+
+  /// Switch to another framing without reopening, e.g. for a device that goes from text commands to binary streaming.
+  ///
+  /// Replaces whatever framing the port was opened with, until it is reopened. A partial frame received under
+  /// the old framing is dropped and reported through `on_error`. Writes queued before the switch keep the old encoding.
+  /// If the switch fails, the old framing and encoding both stay in effect.
+  #[napi]
+  pub fn set_framing(&self, framing: Framing) -> napi::Result<()> {
+    let settings = &self.origin.settings;
+    let framer = Framer::for_framing(&framing, settings)?;
+    // the encoding goes first since it can be put back without losing anything, unlike a partial frame
+    let previous = self.request(&self.write_tx, |reply| {
+      Command::SetEncoding(Encoding::from_framing(&framing), reply)
+    })?;
+    self
+      .request(&self.cmd_tx, |reply| {
+        Command::SetFramer(Box::new(framer), reply)
+      })
+      .inspect_err(|_| {
+        let _ = self.request(&self.write_tx, |reply| {
+          Command::SetEncoding(previous, reply)
+        });
+      })?;
+    *self.state.encoding.lock().unwrap() = Encoding::from_framing(&framing);
+
+    Ok(())
+  }
+
+  /// Stop reading from the port until `resume` is called.
+  ///
+  /// Incoming bytes are left in the OS input buffer rather than discarded, so
+  /// they are delivered after `resume` unless that buffer overflows meanwhile.
+  /// On a `shared` port this pauses the device, i.e. every handle on it.
+  #[napi]
+  pub fn pause(&self) {
+    self.state.paused.store(true, Ordering::Relaxed);
+  }
+
+  /// Resume reading from the port after `pause`, for every handle on a `shared` port
+  #[napi]
+  pub fn resume(&self) {
+    self.state.paused.store(false, Ordering::Relaxed);
+  }
+
+  /// Close the port once every write queued so far has been transmitted
+  #[napi]
+  pub fn close(&mut self, env: &Env) -> napi::Result<()> {
+    self.keep_alive(env, false);
+    self.start_close(None).compute()
+  }
+
+  /// Like `close`, but stops transmitting queued writes after `timeout_ms`.
+  ///
+  /// Returns how many bytes were dropped because they hadn't gone out in time. On a `shared` port only
+  /// the handle that closes the device last sets a deadline, closing any other handle drops nothing.
+  #[napi]
+  pub fn close_with_timeout(&mut self, env: &Env, timeout_ms: u32) -> napi::Result<i64> {
+    self.keep_alive(env, false);
+    self
+      .start_close(Some(Duration::from_millis(timeout_ms as u64)))
+      .compute()?;
+
+    Ok(self.state.dropped_on_close.swap(0, Ordering::Relaxed) as i64)
+  }
+
+  /// Like `close`, but waits for the worker threads off the JS thread
+  #[napi(ts_return_type = "Promise<void>")]
+  pub fn close_async(&mut self, env: &Env) -> AsyncTask<CloseTask> {
+    // the pending promise keeps Node running until the threads are done
+    self.keep_alive(env, false);
+    AsyncTask::new(self.start_close(None))
+  }
+
+  /// Close the port if it is still open, then open it again with the original path, settings and callbacks
+  #[napi]
+  pub fn reopen(&mut self, env: &Env) -> napi::Result<()> {
+    if self.shared.is_some() {
+      return Err(napi::Error::from_reason("a shared port can't be reopened"));
+    }
+
+    self.start_close(None).compute()?;
+
+    // listeners and the paused flag carry over to the new connection
+    self.state.disconnected.store(false, Ordering::Relaxed);
+    *self = start(self.origin.clone(), self.state.clone()).coded(env)?;
+    self.keep_alive(env, true);
+
+    Ok(())
+  }
+
+  /// Replace `on_data_received` without closing, e.g. when a device leaves its bootloader for the application protocol.
+  ///
+  /// The read thread switches between two frames, so every frame goes to exactly one of the two callbacks.
+  #[napi]
+  pub fn set_data_callback(&self, on_data_received: OnDataReceivedCallback) -> napi::Result<()> {
+    let callback = Arc::new(on_data_received);
+    if let Some(id) = self.listener {
+      self.ensure_open()?;
+      self.state.listeners.replace(id, callback);
+      return Ok(());
+    }
+
+    self.request(&self.cmd_tx, |reply| {
+      Command::SetDataCallback(callback.clone(), reply)
+    })?;
+    // `reopen` carries on with the new callback
+    *self.origin.on_data_received.lock().unwrap() = callback;
+
+    Ok(())
+  }
+
+  /// Report how far writes spanning several `write_chunk_size` chunks have got, e.g. for a firmware upload progress bar.
+  ///
+  /// Called after a chunk went out at most every 100 ms, and once more when the last one did. Pass null to stop.
+  #[napi(ts_args_type = "onWriteProgress: ((progress: WriteProgress) => void) | null")]
+  pub fn set_write_progress_callback(
+    &self,
+    on_write_progress: Option<OnWriteProgressCallback>,
+  ) -> napi::Result<()> {
+    self.ensure_writable()?;
+    let callback = on_write_progress.map(Arc::new);
+    self.request(&self.write_tx, |reply| {
+      Command::SetWriteProgress(callback.clone(), reply)
+    })?;
+    // `reopen` carries on with the same callback
+    *self.origin.on_write_progress.lock().unwrap() = callback;
+
+    Ok(())
+  }
+
+  /// Register another callback that receives every frame `onDataReceived` does.
+  ///
+  /// Returns an id to pass to `remove_data_listener`.
+  #[napi]
+  pub fn add_data_listener(&self, listener: OnDataReceivedCallback) -> napi::Result<u32> {
+    self.ensure_open()?;
+    Ok(self.state.listeners.add(Arc::new(listener)))
+  }
+
+  /// Unregister a listener added with `add_data_listener`, returns false if the id is unknown
+  #[napi]
+  pub fn remove_data_listener(&self, id: u32) -> bool {
+    self.state.listeners.remove(id)
+  }
+
+  /// Whether the port can currently be used.
+  ///
+  /// False after `close`, once a worker thread died on an error, and while the device is disconnected.
+  #[napi]
+  pub fn is_open(&self) -> bool {
+    self.kill_tx.is_some()
+      && self.state.running.load(Ordering::Relaxed)
+      && !self.state.disconnected.load(Ordering::Relaxed)
+  }
+
+  /// Path the port was opened with, or the path of the other end for `open_pty_pair`
+  #[napi(getter)]
+  pub fn path(&self) -> String {
+    match &self.pty_slave {
+      Some(slave) => slave.name().unwrap_or_default(),
+      None => self.origin.target.path().to_string(),
+    }
+  }
+
+  /// The `tag` from the settings the port was opened with
+  #[napi(getter)]
+  pub fn tag(&self) -> Option<String> {
+    self.origin.settings.tag.clone()
+  }
+
+  /// Traffic counters accumulated since the port was opened, for the whole device on a `shared` port
+  #[napi]
+  pub fn stats(&self) -> PortStats {
+    self.state.stats.snapshot()
+  }
+}
+
+impl OpenPort {
+  fn ensure_open(&self) -> napi::Result<()> {
+    if self.manual.is_some() {
+      return Err(napi::Error::from_reason(
+        "not available in manualMode, use read and writeSync",
+      ));
+    }
+
+    if self.kill_tx.is_none() {
+      return Err(napi::Error::from_reason("port is closed"));
+    }
+
+    if self.state.disconnected.load(Ordering::Relaxed) {
+      return Err(napi::Error::from_reason("port disconnected"));
+    }
+
+    Ok(())
+  }
+
+  // Signal the worker threads to exit, the returned task joins them
+  fn start_close(&mut self, timeout: Option<Duration>) -> CloseTask {
+    if let Some(id) = self.listener.take() {
+      self.state.listeners.remove(id);
+    }
+    // settles pending `next_frame` calls with null, and lets the read thread stop queueing frames for this handle
+    if let Some(frames) = self.frames.take() {
+      frames.close();
+    }
+    self.manual.take();
+
+    // the write thread goes first, it only exits after working through its queue
+    let mut threads: Vec<_> = [self.write_thread.take(), self.read_thread.take()]
+      .into_iter()
+      .flatten()
+      .collect();
+    let mut closes_device = self.shared.is_none();
+    // held until the deadline is set, the threads must not see the kill channel close before that
+    let mut device_kill_tx = None;
+    if let (Some(shared), Some(_)) = (&self.shared, &self.kill_tx) {
+      let (kill_tx, last_threads) = shared.detach();
+      closes_device = kill_tx.is_some();
+      // the last handle keeps hearing about errors while the worker threads drain
+      if let (None, Some(id)) = (&kill_tx, self.handlers.take()) {
+        self.errors.remove(id);
+      }
+      device_kill_tx = kill_tx;
+      threads.extend(last_threads);
+    }
+
+    // the deadline belongs to whichever handle closes the device, other handles' writes go out before it anyway
+    if closes_device {
+      *self.state.close_deadline.lock().unwrap() = timeout.map(|timeout| Instant::now() + timeout);
+    }
+    drop(device_kill_tx);
+
+    // Close the send side of the kill channel to signal the threads to exit,
+    // this also makes further writes fail while the queue is being drained
+    if self.kill_tx.take().is_some() {
+      ACTIVE_PORTS.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    CloseTask {
+      threads,
+      state: self.state.clone(),
+    }
+  }
+
+  // Whether this handle's callbacks keep Node running, which they should only do while it is open.
+  // `close` can't drop them since `reopen` needs them again.
+  fn keep_alive(&self, env: &Env, alive: bool) {
+    let origin = &self.origin;
+    let mut callbacks = vec![
+      origin.on_data_received.lock().unwrap().raw(),
+      origin.on_error.raw(),
+    ];
+    callbacks.extend(origin.on_disconnect.as_ref().map(|f| f.raw()));
+    callbacks.extend(origin.on_reconnect.as_ref().map(|f| f.raw()));
+    callbacks.extend(origin.on_ready.lock().unwrap().as_ref().map(|f| f.raw()));
+    callbacks.extend(
+      origin
+        .on_write_progress
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|f| f.raw()),
+    );
+    // the listeners of a shared device belong to every handle on it
+    if self.shared.is_none() {
+      callbacks.extend(self.state.listeners.snapshot().iter().map(|(_, f)| f.raw()));
+    }
+
+    for callback in callbacks {
+      unsafe {
+        if alive {
+          napi::sys::napi_ref_threadsafe_function(env.raw(), callback);
+        } else {
+          napi::sys::napi_unref_threadsafe_function(env.raw(), callback);
+        }
+      }
+    }
+  }
+
+  // The handle `read` and `write_sync` use, only there in manual mode
+  fn manual_port(&self) -> napi::Result<std::sync::MutexGuard<'_, NativePort>> {
+    // `close` drops the handle too
+    if self.kill_tx.is_none() {
+      return Err(napi::Error::from_reason("port is closed"));
+    }
+
+    let Some(port) = &self.manual else {
+      return Err(napi::Error::from_reason(
+        "read and writeSync need the port opened with manualMode",
+      ));
+    };
+
+    Ok(port.lock().unwrap())
+  }
+
+  fn ensure_writable(&self) -> napi::Result<()> {
+    if !self.writable {
+      return Err(napi::Error::from_reason("port was opened read-only"));
+    }
+
+    self.ensure_open()
+  }
+
+  fn ensure_readable(&self) -> napi::Result<()> {
+    if !self.readable {
+      return Err(napi::Error::from_reason("port was opened write-only"));
+    }
+
+    self.ensure_open()
+  }
+
+  // Queue a write without waiting, failing if a bounded write queue is full
+  fn enqueue(&self, cmd: Command) -> napi::Result<()> {
+    self.ensure_writable()?;
+    self.write_tx.try_send(cmd).map_err(|e| match e {
+      TrySendError::Full(_) => napi::Error::from_reason("write queue is full"),
+      TrySendError::Disconnected(_) => {
+        napi::Error::from_reason(format!("failed to send write to thread: {e}"))
+      }
+    })
+  }
+
+  // Send a command to a worker thread, returning where its reply will arrive
+  fn send_request<T>(
+    &self,
+    tx: &Sender<Command>,
+    make: impl FnOnce(Reply<T>) -> Command,
+  ) -> napi::Result<Receiver<napi::Result<T>>> {
+    self.ensure_open()?;
+
+    // a full write queue would otherwise block the JS thread until there is room
+    let (reply_tx, reply_rx) = bounded(1);
+    tx.try_send(make(reply_tx)).map_err(|e| match e {
+      TrySendError::Full(_) => napi::Error::from_reason("write queue is full"),
+      TrySendError::Disconnected(_) => napi::Error::from_reason("port is closed"),
+    })?;
+
+    Ok(reply_rx)
+  }
+
+  // Send a command to a worker thread and wait for its reply
+  fn request<T>(
+    &self,
+    tx: &Sender<Command>,
+    make: impl FnOnce(Reply<T>) -> Command,
+  ) -> napi::Result<T> {
+    // the write thread won't get to it before the lock is released, which can't happen while we wait here
+    if tx.same_channel(&self.write_tx) && self.state.locks.load(Ordering::Relaxed) > 0 {
+      return Err(napi::Error::from_reason(
+        "port is locked, release the lock first",
+      ));
+    }
+
+    wait_for_reply(&self.send_request(tx, make)?)
+  }
+
+  // Send a command to a worker thread and wait for its reply off the JS thread
+  fn request_async<T>(
+    &self,
+    tx: &Sender<Command>,
+    make: impl FnOnce(Reply<T>) -> Command,
+  ) -> napi::Result<AsyncTask<ReplyTask<T>>>
+  where
+    ReplyTask<T>: Task,
+  {
+    let rx = self.send_request(tx, make)?;
+    Ok(AsyncTask::new(ReplyTask { rx }))
+  }
+}
+
+// A handle JS garbage collected without `close` shuts down like `close_with_timeout`, but runs on the finalizer:
+// the threads are signalled and left to finish on their own instead of being joined
+impl Drop for OpenPort {
+  fn drop(&mut self) {
+    // already closed, also the old handle `reopen` replaces
+    if self.kill_tx.is_none() {
+      return;
+    }
+
+    drop(self.start_close(Some(DROP_DRAIN_TIMEOUT)));
+  }
+}
+
+// When a transaction's reply is complete, and how long to wait for it at most
+pub(crate) fn transact_until(
+  options: Option<TransactOptions>,
+) -> napi::Result<(ReadUntil, Duration)> {
+  let options = options.unwrap_or(TransactOptions {
+    delimiter: None,
+    length: None,
+    timeout_ms: None,
+    idle_complete_ms: None,
+  });
+
+  let delimiter = match options.delimiter {
+    Some(delimiter) if delimiter.is_empty() => {
+      return Err(napi::Error::from_reason("delimiter must not be empty"));
+    }
+    delimiter => delimiter.map(|d| d.to_vec()),
+  };
+
+  let length = match options.length {
+    Some(0) => return Err(napi::Error::from_reason("length must be greater than 0")),
+    length => length.map(|l| l as usize),
+  };
+
+  let idle = match options.idle_complete_ms {
+    Some(0) => {
+      return Err(napi::Error::from_reason(
+        "idleCompleteMs must be greater than 0",
+      ))
+    }
+    idle => idle.map(|ms| Duration::from_millis(ms as u64)),
+  };
 
-use crate::types::PortSettings;
+  let until = ReadUntil::Reply {
+    delimiter,
+    length,
+    idle,
+  };
+  Ok((
+    until,
+    Duration::from_millis(options.timeout_ms.unwrap_or(1000) as u64),
+  ))
+}
 
-pub type OnDataReceivedCallback = ThreadsafeFunction<Buffer, (), Buffer, napi::Status, false>;
-pub type OnErrorCallback = ThreadsafeFunction<(), ()>;
+fn wait_for_reply<T>(rx: &Receiver<napi::Result<T>>) -> napi::Result<T> {
+  rx.recv()
+    .map_err(|_| napi::Error::from_reason("worker thread exited before replying"))?
+}
 
-#[napi]
-pub struct OpenPort {
-  // thread handles, wrapped in an option so we can join them without having to take self without reference
-  read_thread: Option<thread::JoinHandle<()>>,
-  write_thread: Option<thread::JoinHandle<()>>,
-  // sender for writes
-  write_tx: Sender<Buffer>,
-  // sender is wrapped in an option so we can drop it without having to take self without reference
-  kill_tx: Option<Sender<()>>,
+// Resolves a promise once a command fits in a (possibly full) queue
+pub struct EnqueueTask {
+  tx: Sender<Command>,
+  cmd: Option<Command>,
 }
 
-#[napi]
-impl OpenPort {
-  #[napi]
-  pub fn write(&self, data: Buffer) -> napi::Result<()> {
+impl Task for EnqueueTask {
+  type Output = ();
+  type JsValue = ();
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let cmd = self
+      .cmd
+      .take()
+      .ok_or_else(|| napi::Error::from_reason("write was already queued"))?;
     self
-      .write_tx
-      .send(data)
+      .tx
+      .send(cmd)
       .map_err(|e| napi::Error::from_reason(format!("failed to send write to thread: {e}")))
   }
 
-  #[napi]
-  pub fn close(&mut self) -> napi::Result<()> {
-    // Close the send side of the write channel to signal the threads to exit
-    drop(self.kill_tx.take());
+  fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+}
 
-    // Join worker thread
-    if let Some(handle) = self.write_thread.take() {
-      let _ = handle.join();
+// Joins the worker threads of a closing port
+pub struct CloseTask {
+  threads: Vec<thread::JoinHandle<()>>,
+  state: Arc<SharedState>,
+}
+
+impl Task for CloseTask {
+  type Output = ();
+  type JsValue = ();
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    // nothing to wait for when other handles still share the device
+    if self.threads.is_empty() {
+      return Ok(());
     }
 
-    if let Some(handle) = self.read_thread.take() {
+    for handle in self.threads.drain(..) {
       let _ = handle.join();
     }
 
+    self.state.stats.reset();
+
     Ok(())
   }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
 }
 
-fn apply_builder_settings(
-  mut builder: serialport::SerialPortBuilder,
-  settings: &PortSettings,
-) -> serialport::SerialPortBuilder {
-  // data bits
-  if let Some(db) = &settings.data_bits {
-    let db_enum = match db {
-      crate::types::DataBits::Five => serialport::DataBits::Five,
-      crate::types::DataBits::Six => serialport::DataBits::Six,
-      crate::types::DataBits::Seven => serialport::DataBits::Seven,
-      crate::types::DataBits::Eight => serialport::DataBits::Eight,
-    };
-    builder = builder.data_bits(db_enum);
+// Polls CTS on a handle of its own, off the JS thread.
+// TIOCMIWAIT can't be given a timeout, so a wait that times out would tie up a libuv thread until the line changes.
+pub struct WaitForCtsTask {
+  rx: Receiver<napi::Result<NativePort>>,
+  deadline: Instant,
+}
+
+impl Task for WaitForCtsTask {
+  type Output = bool;
+  type JsValue = bool;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let mut port = wait_for_reply(&self.rx)?;
+    loop {
+      let cts = port
+        .read_clear_to_send()
+        .map_err(|e| napi::Error::from_reason(format!("failed to read CTS: {e}")))?;
+      if cts {
+        return Ok(true);
+      }
+      if Instant::now() >= self.deadline {
+        return Ok(false);
+      }
+      thread::sleep(CTS_POLL_INTERVAL);
+    }
   }
 
-  // parity
-  if let Some(p) = &settings.parity {
-    let p_enum = match p {
-      crate::types::Parity::None => serialport::Parity::None,
-      crate::types::Parity::Odd => serialport::Parity::Odd,
-      crate::types::Parity::Even => serialport::Parity::Even,
-    };
-    builder = builder.parity(p_enum);
+  fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
   }
+}
 
-  // stop bits
-  if let Some(sb) = &settings.stop_bits {
-    let sb_enum = match sb {
-      crate::types::StopBits::One => serialport::StopBits::One,
-      crate::types::StopBits::Two => serialport::StopBits::Two,
-    };
-    builder = builder.stop_bits(sb_enum);
+// Resolves a promise with the reply to a command once the worker sends it
+pub struct ReplyTask<T> {
+  pub(crate) rx: Receiver<napi::Result<T>>,
+}
+
+impl<T> Task for ReplyTask<T>
+where
+  T: ToNapiValue + TypeName + Send + 'static,
+{
+  type Output = T;
+  type JsValue = T;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    wait_for_reply(&self.rx)
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+// Run a command against the native handle owned by the calling thread
+pub(crate) fn execute(port: &mut NativePort, cmd: Command) {
+  match cmd {
+    // writes and one-shot reads are handled by the worker threads themselves
+    Command::Write(..)
+    | Command::WriteBatch(_)
+    | Command::WriteSlow(..)
+    | Command::Write9Bit(..)
+    | Command::ReadOnce(_)
+    | Command::Lock(_)
+    | Command::SubscribeFrames(_)
+    | Command::CancelRead(_)
+    | Command::Transact(_) => {}
+    Command::SetBaudRate(baud, reply) => {
+      let res = port
+        .set_baud_rate(baud)
+        .map_err(|e| napi::Error::from_reason(format!("failed to set baud rate to {baud}: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::SetFlowControl(flow_control, reply) => {
+      let res = port.set_flow_control(flow_control).map_err(|e| {
+        napi::Error::from_reason(format!(
+          "failed to set flow control to {flow_control:?}: {e}"
+        ))
+      });
+      let _ = reply.send(res);
+    }
+    Command::SetTimeout(timeout, reply) => {
+      let res = port
+        .set_timeout(timeout)
+        .map_err(|e| napi::Error::from_reason(format!("failed to set timeout: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::ReadControlLine(line, reply) => {
+      let (name, res) = match line {
+        ControlLine::ClearToSend => ("CTS", port.read_clear_to_send()),
+        ControlLine::DataSetReady => ("DSR", port.read_data_set_ready()),
+        ControlLine::CarrierDetect => ("CD", port.read_carrier_detect()),
+        ControlLine::RingIndicator => ("RI", port.read_ring_indicator()),
+      };
+      let res = res.map_err(|e| {
+        napi::Error::from_reason(format!(
+          "failed to read {name}, it may be unsupported on this port: {e}"
+        ))
+      });
+      let _ = reply.send(res);
+    }
+    Command::SetRts(level, reply) => {
+      // make sure queued bytes are on the wire before touching the handshake lines
+      let res = port
+        .flush()
+        .map_err(serialport::Error::from)
+        .and_then(|_| port.write_request_to_send(level))
+        .map_err(|e| napi::Error::from_reason(format!("failed to set RTS to {level}: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::SetDtr(level, reply) => {
+      let res = port
+        .flush()
+        .map_err(serialport::Error::from)
+        .and_then(|_| port.write_data_terminal_ready(level))
+        .map_err(|e| napi::Error::from_reason(format!("failed to set DTR to {level}: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::SendBreak(duration_ms, reply) => {
+      // nothing else gets written while we hold the break since we own the write side
+      let res = port
+        .flush()
+        .map_err(serialport::Error::from)
+        .and_then(|_| port.set_break())
+        .and_then(|_| {
+          thread::sleep(Duration::from_millis(duration_ms as u64));
+          port.clear_break()
+        })
+        .map_err(|e| napi::Error::from_reason(format!("failed to send break: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::PulseDtr(low_ms, reply) => {
+      // the level before the pulse can't be read back, so DTR ends up raised
+      let res = port
+        .flush()
+        .map_err(serialport::Error::from)
+        .and_then(|_| port.write_data_terminal_ready(false))
+        .and_then(|_| {
+          thread::sleep(Duration::from_millis(low_ms as u64));
+          port.write_data_terminal_ready(true)
+        })
+        .map_err(|e| napi::Error::from_reason(format!("failed to pulse DTR: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::SetBreak(asserted, reply) => {
+      // let queued bytes finish going out so the break starts after them
+      let res = port
+        .flush()
+        .map_err(serialport::Error::from)
+        .and_then(|_| {
+          if asserted {
+            port.set_break()
+          } else {
+            port.clear_break()
+          }
+        })
+        .map_err(|e| napi::Error::from_reason(format!("failed to set break: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::Clear(buffer, reply) => {
+      let res = port
+        .clear(buffer)
+        .map_err(|e| napi::Error::from_reason(format!("failed to clear {buffer:?} buffer: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::Drain(reply) => {
+      let res = port
+        .flush()
+        .map_err(|e| napi::Error::from_reason(format!("failed to drain: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::BytesToRead(reply) => {
+      let res = port
+        .bytes_to_read()
+        .map_err(|e| napi::Error::from_reason(format!("failed to query bytes to read: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::BytesToWrite(reply) => {
+      let res = port
+        .bytes_to_write()
+        .map_err(|e| napi::Error::from_reason(format!("failed to query bytes to write: {e}")));
+      let _ = reply.send(res);
+    }
+    Command::CurrentSettings(reply) => {
+      let _ = reply.send(current_settings(port));
+    }
+    Command::Describe(reply) => {
+      let _ = reply.send(describe(port));
+    }
+    Command::RawHandle(reply) => {
+      let _ = reply.send(Ok(raw_handle(port)));
+    }
+    // only the read thread buffers frames and only the write thread encodes, anywhere else there is nothing to change
+    Command::SetEncoding(encoding, reply) => {
+      let _ = reply.send(Ok(encoding));
+    }
+    Command::SetFramer(_, reply)
+    | Command::ResetFrameBuffer(reply)
+    | Command::SetDataCallback(_, reply)
+    | Command::SetWriteProgress(_, reply) => {
+      let _ = reply.send(Ok(()));
+    }
+    Command::CloneHandle(reply) => {
+      let res = port
+        .try_clone_native()
+        .map_err(|e| napi::Error::from_reason(format!("failed to clone the port handle: {e}")));
+      let _ = reply.send(res);
+    }
   }
+}
 
-  // flow control
-  if let Some(fc) = &settings.flow_control {
-    let fc_enum = match fc {
-      crate::types::FlowControl::None => serialport::FlowControl::None,
-      crate::types::FlowControl::Software => serialport::FlowControl::Software,
-      crate::types::FlowControl::Hardware => serialport::FlowControl::Hardware,
+fn current_settings(port: &NativePort) -> napi::Result<PortSettings> {
+  let err =
+    |e: serialport::Error| napi::Error::from_reason(format!("failed to read settings: {e}"));
+
+  let data_bits = port.data_bits().map_err(err)?;
+  let parity = match stick_parity(port) {
+    Some(true) => Parity::Mark,
+    Some(false) => Parity::Space,
+    None => port.parity().map_err(err)?.into(),
+  };
+  let stop_bits = if has_one_point_five_stop_bits(port) {
+    StopBits::OnePointFive
+  } else {
+    match (data_bits, port.stop_bits().map_err(err)?) {
+      // see `LineSettings::new`
+      (serialport::DataBits::Five, serialport::StopBits::Two) if cfg!(unix) => {
+        StopBits::OnePointFive
+      }
+      (_, stop_bits) => stop_bits.into(),
+    }
+  };
+
+  Ok(PortSettings {
+    baud_rate: Some(port.baud_rate().map_err(err)?),
+    timeout_ms: Some(port.timeout().as_millis() as u32),
+    data_bits: Some(data_bits.into()),
+    parity: Some(parity),
+    stop_bits: Some(stop_bits),
+    flow_control: Some(port.flow_control().map_err(err)?.into()),
+    ..Default::default()
+  })
+}
+
+fn describe(port: &mut NativePort) -> napi::Result<PortDescription> {
+  let settings = current_settings(port)?;
+
+  Ok(PortDescription {
+    // filled in by `OpenPort::describe`, the handle may not know the path it was opened with
+    path: String::new(),
+    baud_rate: settings.baud_rate.unwrap_or_default(),
+    timeout_ms: settings.timeout_ms.unwrap_or_default(),
+    data_bits: settings.data_bits.unwrap_or(DataBits::Eight),
+    parity: settings.parity.unwrap_or(Parity::None),
+    stop_bits: settings.stop_bits.unwrap_or(StopBits::One),
+    flow_control: settings.flow_control.unwrap_or(FlowControl::None),
+    #[cfg(unix)]
+    exclusive: Some(port.exclusive()),
+    #[cfg(windows)]
+    exclusive: None,
+    low_latency: has_low_latency(port),
+    bytes_to_read: port.bytes_to_read().ok(),
+    bytes_to_write: port.bytes_to_write().ok(),
+    clear_to_send: port.read_clear_to_send().ok(),
+    data_set_ready: port.read_data_set_ready().ok(),
+    carrier_detect: port.read_carrier_detect().ok(),
+    ring_indicator: port.read_ring_indicator().ok(),
+  })
+}
+
+// The line settings a device is opened with, kept so a reopened device gets them again
+#[derive(Clone)]
+pub(crate) struct LineSettings {
+  pub baud_rate: u32,
+  pub data_bits: serialport::DataBits,
+  pub parity: serialport::Parity,
+  pub stop_bits: serialport::StopBits,
+  pub flow_control: serialport::FlowControl,
+  pub timeout: Duration,
+  // set as part of opening, before any data can flow
+  pub dtr_on_open: Option<bool>,
+}
+
+impl LineSettings {
+  fn new(settings: &PortSettings, baud_rate: u32, timeout: Duration) -> Self {
+    let data_bits = match settings.data_bits {
+      Some(DataBits::Five) => serialport::DataBits::Five,
+      Some(DataBits::Six) => serialport::DataBits::Six,
+      Some(DataBits::Seven) => serialport::DataBits::Seven,
+      Some(DataBits::Eight) | None => serialport::DataBits::Eight,
+    };
+
+    let parity = match settings.parity {
+      Some(Parity::None) | None => serialport::Parity::None,
+      // `enable_stick_parity` turns these into mark and space after opening
+      Some(Parity::Odd | Parity::Mark) => serialport::Parity::Odd,
+      Some(Parity::Even | Parity::Space) => serialport::Parity::Even,
+    };
+
+    let stop_bits = match settings.stop_bits {
+      Some(StopBits::One) | None => serialport::StopBits::One,
+      // termios has no 1.5, the UART sends 1.5 stop bits for CSTOPB when characters are 5 bits wide.
+      // Windows has a setting of its own, which `enable_one_point_five_stop_bits` switches to after opening
+      Some(StopBits::OnePointFive) if cfg!(windows) => serialport::StopBits::One,
+      Some(StopBits::OnePointFive | StopBits::Two) => serialport::StopBits::Two,
+    };
+
+    let flow_control = match settings.flow_control {
+      Some(FlowControl::None) | None => serialport::FlowControl::None,
+      Some(FlowControl::Software) => serialport::FlowControl::Software,
+      Some(FlowControl::Hardware) => serialport::FlowControl::Hardware,
     };
-    builder = builder.flow_control(fc_enum);
+
+    LineSettings {
+      baud_rate,
+      data_bits,
+      parity,
+      stop_bits,
+      flow_control,
+      timeout,
+      dtr_on_open: settings.initial_dtr,
+    }
+  }
+
+  fn builder(&self, path: &str) -> serialport::SerialPortBuilder {
+    let builder = serialport::new(path, self.baud_rate)
+      .data_bits(self.data_bits)
+      .parity(self.parity)
+      .stop_bits(self.stop_bits)
+      .flow_control(self.flow_control)
+      .timeout(self.timeout);
+
+    match self.dtr_on_open {
+      Some(dtr) => builder.dtr_on_open(dtr),
+      None => builder,
+    }
   }
 
-  builder
+  // Configure a port that was opened without going through the builder
+  fn apply(&self, port: &mut NativePort) -> serialport::Result<()> {
+    port.set_baud_rate(self.baud_rate)?;
+    port.set_data_bits(self.data_bits)?;
+    port.set_parity(self.parity)?;
+    port.set_stop_bits(self.stop_bits)?;
+    port.set_flow_control(self.flow_control)?;
+    port.set_timeout(self.timeout)?;
+    // best effort, like `open_native`: pseudo terminals have no DTR
+    if let Some(dtr) = self.dtr_on_open {
+      let _ = port.write_data_terminal_ready(dtr);
+    }
+
+    Ok(())
+  }
 }
 
-// Make the opened port non-exclusive on platforms that support it.
+// Set whether other processes may open the port too, on platforms that support it.
 // On Unix-like platforms the underlying TTY port supports `set_exclusive`.
-// On Windows this is a no-op because the COM port implementation doesn't expose it.
+// On Windows this is a no-op because COM ports are always opened exclusively.
 #[cfg(unix)]
-fn make_port_nonexclusive(port: &mut serialport::TTYPort, path: &str) -> napi::Result<()> {
-  port.set_exclusive(false).map_err(|e| {
-    napi::Error::from_reason(format!("failed to make the port {path} not exclusive: {e}"))
+fn set_port_exclusivity(
+  port: &mut serialport::TTYPort,
+  exclusive: bool,
+  path: &str,
+) -> napi::Result<()> {
+  port.set_exclusive(exclusive).map_err(|e| {
+    napi::Error::from_reason(format!(
+      "failed to set the exclusivity of {path} to {exclusive}: {e}"
+    ))
   })
 }
 
 // No-op on Windows
 #[cfg(windows)]
-fn make_port_nonexclusive(_: &mut serialport::COMPort, _: &str) -> napi::Result<()> {
+fn set_port_exclusivity(_: &mut serialport::COMPort, _: bool, _: &str) -> napi::Result<()> {
   Ok(())
 }
 
+// Open the device and the clone used by the write thread, for just the directions in use
+pub(crate) fn open_native_pair(
+  line: &LineSettings,
+  path: &str,
+  exclusive: bool,
+  initial_rts: Option<bool>,
+  readable: bool,
+  writable: bool,
+) -> napi::Result<(NativePort, NativePort)> {
+  let opened = if readable && writable {
+    let builder = line.builder(path);
+    // asking for an exclusive lock first would fail while anyone else holds a shared one
+    #[cfg(unix)]
+    let builder = builder.exclusive(exclusive);
+    builder.open_native().map_err(|e| {
+      (
+        PortError::from_serialport("failed to open", &e),
+        is_busy(&e, path),
+      )
+    })
+  } else {
+    open_one_way(path, writable)
+      .map_err(|e| (PortError::from_io("failed to open", &e), is_busy_io(&e)))
+      .and_then(|mut port| {
+        line
+          .apply(&mut port)
+          .map(|_| port)
+          .map_err(|e| (PortError::from_serialport("failed to open", &e), false))
+      })
+  };
+  let mut read_port = opened.map_err(|(e, busy)| {
+    if busy {
+      PortError {
+        kind: PortErrorKind::PortBusy,
+        message: format!("{}, {path} is in use by another application", e.message),
+        ..e
+      }
+    } else {
+      e
+    }
+  })?;
+
+  set_port_exclusivity(&mut read_port, exclusive, path)?;
+
+  if let Some(level) = initial_rts {
+    read_port
+      .write_request_to_send(level)
+      .map_err(|e| napi::Error::from_reason(format!("failed to set the initial RTS level: {e}")))?;
+  }
+
+  let write_port = read_port
+    .try_clone_native()
+    .map_err(|e| napi::Error::from_reason(format!("failed to clone port: {e}")))?;
+
+  Ok((read_port, write_port))
+}
+
+// Both ends of a pseudo terminal, everything written to the first comes out of the second
+#[cfg(unix)]
+fn open_loopback_pair(
+  builder: serialport::SerialPortBuilder,
+) -> napi::Result<(NativePort, NativePort)> {
+  let map_err =
+    |e: serialport::Error| napi::Error::from_reason(format!("failed to create loopback port: {e}"));
+
+  let (mut master, mut slave) = serialport::TTYPort::pair().map_err(map_err)?;
+  // reopen the slave end through the builder so it gets the requested settings,
+  // shared since the first handle on it is still open
+  slave.set_exclusive(false).map_err(map_err)?;
+  let path = slave.name().unwrap_or_default();
+  let read_port = builder
+    .path(path)
+    .exclusive(false)
+    .open_native()
+    .map_err(map_err)?;
+  master.set_timeout(read_port.timeout()).map_err(map_err)?;
+
+  Ok((read_port, master))
+}
+
+#[cfg(windows)]
+fn open_loopback_pair(_: serialport::SerialPortBuilder) -> napi::Result<(NativePort, NativePort)> {
+  Err(napi::Error::from_reason(
+    "loopback ports are only available on Unix",
+  ))
+}
+
+// The master end of a pseudo terminal twice, plus a handle on the slave end that
+// stays open so the master keeps working while nobody else has the slave open
+#[cfg(unix)]
+fn open_pty_pair(
+  builder: serialport::SerialPortBuilder,
+) -> napi::Result<(NativePort, NativePort, NativePort)> {
+  let map_err =
+    |e: serialport::Error| napi::Error::from_reason(format!("failed to create pty pair: {e}"));
+
+  let (mut master, mut slave) = serialport::TTYPort::pair().map_err(map_err)?;
+  slave.set_exclusive(false).map_err(map_err)?;
+  // reopen the slave end through the builder so it starts out with the requested settings,
+  // and without locking out whoever is meant to open it
+  let path = slave.name().unwrap_or_default();
+  let slave = builder
+    .path(path)
+    .exclusive(false)
+    .open_native()
+    .map_err(map_err)?;
+
+  master.set_timeout(slave.timeout()).map_err(map_err)?;
+  let write_port = master.try_clone_native().map_err(map_err)?;
+
+  Ok((master, write_port, slave))
+}
+
+#[cfg(windows)]
+fn open_pty_pair(
+  _: serialport::SerialPortBuilder,
+) -> napi::Result<(NativePort, NativePort, NativePort)> {
+  Err(napi::Error::from_reason(
+    "pty pairs are only available on Unix",
+  ))
+}
+
+// What `open` should connect to
+enum Target {
+  Device(String),
+  // a pseudo terminal that echoes every write back
+  Loopback,
+  // the master end of a fresh pseudo terminal, its slave end is left for someone else to open
+  Pty,
+}
+
+impl Origin {
+  fn error_handlers(&self) -> ErrorHandlers {
+    ErrorHandlers {
+      on_error: self.on_error.clone(),
+      on_disconnect: self.on_disconnect.clone(),
+      on_reconnect: self.on_reconnect.clone(),
+      tag: self.settings.tag.clone(),
+    }
+  }
+}
+
+impl Target {
+  fn path(&self) -> &str {
+    match self {
+      Target::Device(path) => path,
+      Target::Loopback => "loopback",
+      Target::Pty => "pty",
+    }
+  }
+}
+
+// Everything a port was opened with, kept so `reopen` can do it again
+pub(crate) struct Origin {
+  target: Target,
+  settings: PortSettings,
+  // replaced by `set_data_callback`
+  on_data_received: Mutex<Arc<OnDataReceivedCallback>>,
+  on_error: Arc<OnErrorCallback>,
+  on_disconnect: Option<Arc<OnErrorCallback>>,
+  on_reconnect: Option<Arc<OnReconnectCallback>>,
+  // taken by whichever worker thread starts first, so it is only ever called once
+  on_ready: Mutex<Option<OnReadyCallback>>,
+  // set by `set_write_progress_callback`
+  on_write_progress: Mutex<Option<Arc<OnWriteProgressCallback>>>,
+}
+
 pub fn open_port(
   path: &str,
-  on_data_received: ThreadsafeFunction<Buffer, (), Buffer, napi::Status, false>,
-  on_error: ThreadsafeFunction<(), ()>,
+  on_data_received: OnDataReceivedCallback,
+  on_error: OnErrorCallback,
+  settings: Option<PortSettings>,
+  on_disconnect: Option<OnErrorCallback>,
+  on_reconnect: Option<OnReconnectCallback>,
+  on_ready: Option<OnReadyCallback>,
+) -> napi::Result<OpenPort> {
+  open(
+    Target::Device(path.to_string()),
+    on_data_received,
+    on_error,
+    settings,
+    on_disconnect,
+    on_reconnect,
+    on_ready,
+  )
+  .compute()
+}
+
+// Like `open_port`, but the opening happens when the returned task runs
+pub fn open_port_async(
+  path: &str,
+  on_data_received: OnDataReceivedCallback,
+  on_error: OnErrorCallback,
+  settings: Option<PortSettings>,
+  on_disconnect: Option<OnErrorCallback>,
+  on_reconnect: Option<OnReconnectCallback>,
+  on_ready: Option<OnReadyCallback>,
+) -> OpenTask {
+  open(
+    Target::Device(path.to_string()),
+    on_data_received,
+    on_error,
+    settings,
+    on_disconnect,
+    on_reconnect,
+    on_ready,
+  )
+}
+
+pub fn open_pty_port(
+  on_data_received: OnDataReceivedCallback,
+  on_error: OnErrorCallback,
+  settings: Option<PortSettings>,
+) -> napi::Result<OpenPort> {
+  open(
+    Target::Pty,
+    on_data_received,
+    on_error,
+    settings,
+    None,
+    None,
+    None,
+  )
+  .compute()
+}
+
+pub fn open_loopback_port(
+  on_data_received: OnDataReceivedCallback,
+  on_error: OnErrorCallback,
   settings: Option<PortSettings>,
 ) -> napi::Result<OpenPort> {
+  open(
+    Target::Loopback,
+    on_data_received,
+    on_error,
+    settings,
+    None,
+    None,
+    None,
+  )
+  .compute()
+}
+
+fn open(
+  target: Target,
+  on_data_received: OnDataReceivedCallback,
+  on_error: OnErrorCallback,
+  settings: Option<PortSettings>,
+  on_disconnect: Option<OnErrorCallback>,
+  on_reconnect: Option<OnReconnectCallback>,
+  on_ready: Option<OnReadyCallback>,
+) -> OpenTask {
   let settings = settings.unwrap_or(PortSettings {
     baud_rate: Some(115_200),
     timeout_ms: Some(10),
@@ -129,89 +1862,687 @@ pub fn open_port(
     parity: Some(crate::types::Parity::None),
     stop_bits: Some(crate::types::StopBits::One),
     flow_control: Some(crate::types::FlowControl::None),
+    ..Default::default()
+  });
+
+  let origin = Origin {
+    target,
+    settings,
+    on_data_received: Mutex::new(Arc::new(on_data_received)),
+    on_error: Arc::new(on_error),
+    on_disconnect: on_disconnect.map(Arc::new),
+    on_reconnect: on_reconnect.map(Arc::new),
+    on_ready: Mutex::new(on_ready),
+    on_write_progress: Mutex::new(None),
+  };
+
+  OpenTask {
+    origin: Arc::new(origin),
+  }
+}
+
+// Opens a port and spawns its worker threads, off the JS thread when run as an `AsyncTask`
+pub struct OpenTask {
+  origin: Arc<Origin>,
+}
+
+impl Task for OpenTask {
+  type Output = OpenPort;
+  type JsValue = OpenPort;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    match &self.origin.target {
+      Target::Device(path) if self.origin.settings.shared.unwrap_or(false) => {
+        open_shared(path, self.origin.clone())
+      }
+      _ => start(self.origin.clone(), Arc::new(SharedState::default())),
+    }
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+    Err(coded(&env, err))
+  }
+}
+
+// Ports opened in shared mode, so opening the same path again can join them
+static SHARED_PORTS: Mutex<Vec<Weak<SharedPort>>> = Mutex::new(Vec::new());
+
+// A device opened in shared mode, with one set of worker threads for all of its handles
+pub(crate) struct SharedPort {
+  path: String,
+  origin: Arc<Origin>,
+  write_tx: Sender<Command>,
+  priority_tx: Sender<Command>,
+  cmd_tx: Sender<Command>,
+  state: Arc<SharedState>,
+  readable: bool,
+  writable: bool,
+  // every handle's error callbacks, so they keep hearing about errors whichever handle closes first
+  errors: Arc<ErrorSink>,
+  handles: Mutex<SharedHandles>,
+}
+
+struct SharedHandles {
+  open: usize,
+  // both are given up by the last handle to close, which stops the worker threads
+  kill_tx: Option<Sender<()>>,
+  threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl SharedPort {
+  // Another handle on the device, None once its last handle closed
+  fn attach(&self, origin: Arc<Origin>) -> Option<OpenPort> {
+    let mut handles = self.handles.lock().unwrap();
+    let kill_tx = handles.kill_tx.clone()?;
+    handles.open += 1;
+    ACTIVE_PORTS.fetch_add(1, Ordering::Relaxed);
+
+    let on_data_received = origin.on_data_received.lock().unwrap().clone();
+
+    Some(OpenPort {
+      read_thread: None,
+      write_thread: None,
+      write_tx: self.write_tx.clone(),
+      priority_tx: self.priority_tx.clone(),
+      cmd_tx: self.cmd_tx.clone(),
+      kill_tx: Some(kill_tx),
+      state: self.state.clone(),
+      pty_slave: None,
+      readable: self.readable,
+      writable: self.writable,
+      shared: None,
+      listener: Some(self.state.listeners.add(on_data_received)),
+      errors: self.errors.clone(),
+      handlers: Some(self.errors.add(origin.error_handlers())),
+      origin,
+      frames: OnceLock::new(),
+      manual: None,
+    })
+  }
+
+  // Whether the last handle already let go, a handle opening the path now opens the device anew
+  fn closed(&self) -> bool {
+    self.handles.lock().unwrap().kill_tx.is_none()
+  }
+
+  // Let go of one handle, handing back what stops the worker threads if it was the last
+  fn detach(&self) -> (Option<Sender<()>>, Vec<thread::JoinHandle<()>>) {
+    let mut handles = self.handles.lock().unwrap();
+    handles.open -= 1;
+    if handles.open > 0 {
+      return (None, Vec::new());
+    }
+
+    (handles.kill_tx.take(), std::mem::take(&mut handles.threads))
+  }
+}
+
+// Whether a handle opened with `settings` may join a device opened with `opened`: the worker threads serve every
+// handle the same way, so only the tag may differ
+fn joinable(opened: &PortSettings, settings: &PortSettings) -> bool {
+  fn delimiters(delimiters: &[Buffer]) -> Vec<&[u8]> {
+    delimiters
+      .iter()
+      .map(|delimiter| delimiter.as_ref())
+      .collect()
+  }
+
+  let PortSettings {
+    baud_rate,
+    timeout_ms,
+    data_bits,
+    parity,
+    stop_bits,
+    flow_control,
+    read_buffer_size,
+    os_read_buffer_bytes,
+    low_latency,
+    discard_ms_after_open,
+    max_idle_poll_ms,
+    tag: _,
+    exclusive,
+    initial_dtr,
+    initial_rts,
+    auto_reconnect,
+    reconnect_interval_ms,
+    liveness_poll_ms,
+    rs485,
+    suppress_local_echo,
+    local_echo_window_ms,
+    write_timeout_ms,
+    write_chunk_size,
+    write_coalesce_ms,
+    write_coalesce_count,
+    write_queue_capacity,
+    min_read_bytes,
+    min_read_timeout_ms,
+    framing,
+    frame_delimiter,
+    frame_delimiters,
+    include_delimiter,
+    frame_length,
+    inter_byte_timeout_ms,
+    max_frame_size,
+    frame_overflow,
+    encoding,
+    strict_decoding,
+    callback_mode,
+    callback_errors,
+    report_parity_errors,
+    trace_file,
+    trace_file_max_bytes,
+    shared,
+    access_mode,
+    manual_mode,
+    timestamp_reads,
+  } = opened;
+
+  *baud_rate == settings.baud_rate
+    && *timeout_ms == settings.timeout_ms
+    && *data_bits == settings.data_bits
+    && *parity == settings.parity
+    && *stop_bits == settings.stop_bits
+    && *flow_control == settings.flow_control
+    && *read_buffer_size == settings.read_buffer_size
+    && *os_read_buffer_bytes == settings.os_read_buffer_bytes
+    && *low_latency == settings.low_latency
+    && *discard_ms_after_open == settings.discard_ms_after_open
+    && *max_idle_poll_ms == settings.max_idle_poll_ms
+    && *exclusive == settings.exclusive
+    && *initial_dtr == settings.initial_dtr
+    && *initial_rts == settings.initial_rts
+    && *auto_reconnect == settings.auto_reconnect
+    && *reconnect_interval_ms == settings.reconnect_interval_ms
+    && *liveness_poll_ms == settings.liveness_poll_ms
+    && *rs485 == settings.rs485
+    && *suppress_local_echo == settings.suppress_local_echo
+    && *local_echo_window_ms == settings.local_echo_window_ms
+    && *write_timeout_ms == settings.write_timeout_ms
+    && *write_chunk_size == settings.write_chunk_size
+    && *write_coalesce_ms == settings.write_coalesce_ms
+    && *write_coalesce_count == settings.write_coalesce_count
+    && *write_queue_capacity == settings.write_queue_capacity
+    && *min_read_bytes == settings.min_read_bytes
+    && *min_read_timeout_ms == settings.min_read_timeout_ms
+    && *framing == settings.framing
+    && frame_delimiter.as_deref() == settings.frame_delimiter.as_deref()
+    && frame_delimiters.as_deref().map(delimiters)
+      == settings.frame_delimiters.as_deref().map(delimiters)
+    && *include_delimiter == settings.include_delimiter
+    && *frame_length == settings.frame_length
+    && *inter_byte_timeout_ms == settings.inter_byte_timeout_ms
+    && *max_frame_size == settings.max_frame_size
+    && *frame_overflow == settings.frame_overflow
+    && *encoding == settings.encoding
+    && *strict_decoding == settings.strict_decoding
+    && *callback_mode == settings.callback_mode
+    && *callback_errors == settings.callback_errors
+    && *report_parity_errors == settings.report_parity_errors
+    && *trace_file == settings.trace_file
+    && *trace_file_max_bytes == settings.trace_file_max_bytes
+    && *shared == settings.shared
+    && *access_mode == settings.access_mode
+    && *manual_mode == settings.manual_mode
+    && *timestamp_reads == settings.timestamp_reads
+}
+
+// Join the device other handles already opened on `path` in shared mode, or open it
+fn open_shared(path: &str, origin: Arc<Origin>) -> napi::Result<OpenPort> {
+  // held while opening, so two handles can't both end up opening the device
+  let mut ports = SHARED_PORTS.lock().unwrap();
+  ports.retain(|port| port.strong_count() > 0);
+
+  for port in ports.iter().filter_map(Weak::upgrade) {
+    if port.path == path {
+      if !port.closed() && !joinable(&port.origin.settings, &origin.settings) {
+        return Err(napi::Error::from_reason(format!(
+          "{path} is already open in shared mode with other settings"
+        )));
+      }
+      if let Some(mut handle) = port.attach(origin.clone()) {
+        handle.shared = Some(port);
+        // the worker threads are long up and running
+        if let Some(on_ready) = origin.on_ready.lock().unwrap().take() {
+          let _ = on_ready.call((), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+        return Ok(handle);
+      }
+    }
+  }
+
+  let mut handle = start(origin.clone(), Arc::new(SharedState::default()))?;
+  let port = Arc::new(SharedPort {
+    path: path.to_string(),
+    origin,
+    write_tx: handle.write_tx.clone(),
+    priority_tx: handle.priority_tx.clone(),
+    cmd_tx: handle.cmd_tx.clone(),
+    state: handle.state.clone(),
+    readable: handle.readable,
+    writable: handle.writable,
+    errors: handle.errors.clone(),
+    handles: Mutex::new(SharedHandles {
+      open: 1,
+      kill_tx: handle.kill_tx.clone(),
+      threads: [handle.write_thread.take(), handle.read_thread.take()]
+        .into_iter()
+        .flatten()
+        .collect(),
+    }),
   });
+  ports.push(Arc::downgrade(&port));
+  handle.shared = Some(port);
+
+  Ok(handle)
+}
+
+// Open the port described by `origin` and spawn its worker threads
+fn start(origin: Arc<Origin>, state: Arc<SharedState>) -> napi::Result<OpenPort> {
+  let settings = &origin.settings;
+
+  let framer = Framer::from_settings(settings)?;
+
+  if matches!(settings.stop_bits, Some(StopBits::OnePointFive))
+    && !matches!(settings.data_bits, Some(DataBits::Five))
+  {
+    return Err(napi::Error::from_reason(
+      "1.5 stop bits are unsupported with dataBits other than Five",
+    ));
+  }
+
+  let read_buffer_size = match settings.read_buffer_size {
+    Some(size) if size == 0 || size > MAX_READ_BUFFER_SIZE => {
+      return Err(napi::Error::from_reason(format!(
+        "readBufferSize must be between 1 and {MAX_READ_BUFFER_SIZE}"
+      )));
+    }
+    Some(size) => size as usize,
+    None => 1024,
+  };
+
+  let coalesce = match settings.min_read_bytes {
+    Some(0) => {
+      return Err(napi::Error::from_reason(
+        "minReadBytes must be greater than 0",
+      ))
+    }
+    Some(min_bytes) => Some(Coalesce {
+      min_bytes: min_bytes as usize,
+      timeout: Duration::from_millis(settings.min_read_timeout_ms.unwrap_or(20) as u64),
+      held: Vec::new(),
+      since: Instant::now(),
+    }),
+    None => None,
+  };
+
+  let chunk_size = match settings.write_chunk_size {
+    Some(0) => {
+      return Err(napi::Error::from_reason(
+        "writeChunkSize must be greater than 0",
+      ))
+    }
+    chunk_size => chunk_size.map(|size| size as usize),
+  };
+
+  let write_coalesce = match (settings.write_coalesce_ms, settings.write_coalesce_count) {
+    (_, Some(0)) => {
+      return Err(napi::Error::from_reason(
+        "writeCoalesceCount must be greater than 0",
+      ))
+    }
+    (None, None) => None,
+    (window_ms, max_writes) => Some(WriteCoalesce {
+      window: Duration::from_millis(window_ms.unwrap_or(0) as u64),
+      max_writes: max_writes.map_or(usize::MAX, |max| max as usize),
+    }),
+  };
+
+  // all of these need a worker thread, which manual mode doesn't start
+  if settings.manual_mode.unwrap_or(false) {
+    let needs_threads = [
+      ("shared", settings.shared.unwrap_or(false)),
+      ("autoReconnect", settings.auto_reconnect.unwrap_or(false)),
+      ("framing", !framer.is_raw()),
+      ("encoding", settings.encoding.is_some()),
+      ("traceFile", settings.trace_file.is_some()),
+      ("rs485", settings.rs485.is_some()),
+      ("onReady", origin.on_ready.lock().unwrap().is_some()),
+    ];
+    if let Some((name, _)) = needs_threads.iter().find(|(_, set)| *set) {
+      return Err(napi::Error::from_reason(format!(
+        "manualMode can't be combined with {name}"
+      )));
+    }
+  }
+
+  // the device is only opened for the directions in use, and only their threads run
+  let (readable, writable) = match settings.access_mode {
+    Some(AccessMode::Read) => (true, false),
+    Some(AccessMode::Write) => (false, true),
+    Some(AccessMode::ReadWrite) | None => (true, true),
+  };
 
   let baud = settings.baud_rate.unwrap_or(115_200);
   let timeout = Duration::from_millis(settings.timeout_ms.unwrap_or(10) as u64);
 
-  let builder = serialport::new(path, baud);
-  let builder = apply_builder_settings(builder, &settings).timeout(timeout);
+  let path = origin.target.path();
+  let line = LineSettings::new(settings, baud, timeout);
 
-  let mut read_port = builder
-    .open_native()
-    .map_err(|e| napi::Error::from_reason(format!("failed to open: {e}")))?;
+  let exclusive = settings.exclusive.unwrap_or(false);
+  let mut pty_slave = None;
+  let (read_port, mut write_port) = match &origin.target {
+    Target::Device(path) => open_native_pair(
+      &line,
+      path,
+      exclusive,
+      settings.initial_rts,
+      readable,
+      writable,
+    )?,
+    Target::Loopback => open_loopback_pair(line.builder(path))?,
+    Target::Pty => {
+      let (read_port, write_port, slave) = open_pty_pair(line.builder(path))?;
+      pty_slave = Some(slave);
+      (read_port, write_port)
+    }
+  };
+  // errors are better labelled with the path other code actually opens
+  let errors = Arc::new(ErrorSink::new(match &pty_slave {
+    Some(slave) => slave.name().unwrap_or_default(),
+    None => path.to_string(),
+  }));
+  let handlers = errors.add(origin.error_handlers());
+
+  let one_point_five_stop_bits = matches!(settings.stop_bits, Some(StopBits::OnePointFive));
+  if one_point_five_stop_bits {
+    enable_one_point_five_stop_bits(&read_port)
+      .map_err(|e| napi::Error::from_reason(format!("failed to set up 1.5 stop bits: {e}")))?;
+  }
 
-  make_port_nonexclusive(&mut read_port, path)?;
+  let stick_parity = matches!(settings.parity, Some(Parity::Mark | Parity::Space));
+  if stick_parity {
+    enable_stick_parity(&read_port).map_err(|e| {
+      napi::Error::from_reason(format!("failed to set up mark or space parity: {e}"))
+    })?;
+  }
 
-  let mut write_port = read_port
-    .try_clone_native()
-    .map_err(|e| napi::Error::from_reason(format!("failed to clone port: {e}")))?;
+  let low_latency = settings.low_latency.unwrap_or(false);
+
+  let trace = match &settings.trace_file {
+    Some(path) => {
+      let max_size = settings.trace_file_max_bytes.unwrap_or(10 * 1024 * 1024) as u64;
+      let trace = Trace::open(path, max_size)
+        .map_err(|e| napi::Error::from_reason(format!("failed to open trace file {path}: {e}")))?;
+      Some(Arc::new(trace))
+    }
+    None => None,
+  };
+
+  let echo = match (settings.suppress_local_echo, settings.local_echo_window_ms) {
+    (_, Some(0)) => {
+      return Err(napi::Error::from_reason(
+        "localEchoWindowMs must be greater than 0",
+      ))
+    }
+    (Some(true), window) => Some(Arc::new(LocalEcho::new(Duration::from_millis(
+      window.unwrap_or(100) as u64,
+    )))),
+    _ => None,
+  };
+
+  let parity_marks = if settings.report_parity_errors.unwrap_or(false) {
+    enable_parity_marking(&read_port).map_err(|e| {
+      napi::Error::from_reason(format!("failed to enable parity error reporting: {e}"))
+    })?;
+    Some(ParityMarks::default())
+  } else {
+    None
+  };
+
+  // prefer letting the kernel drive RTS, toggle it by hand where that isn't possible
+  let rs485 = match &settings.rs485 {
+    Some(rs485) if enable_kernel_rs485(&read_port, rs485).is_err() => {
+      let manual = ManualRs485 {
+        rts_on_send: rs485.rts_on_send.unwrap_or(true),
+        rts_when_idle: bus_is_idle_high(rs485),
+        after_send_delay: Duration::from_micros(rs485.rts_after_send_delay_us.unwrap_or(0) as u64),
+      };
+      // park the transceiver in receive mode
+      write_port
+        .write_request_to_send(manual.rts_when_idle)
+        .map_err(|e| napi::Error::from_reason(format!("failed to set up RS-485: {e}")))?;
+      Some(manual)
+    }
+    _ => None,
+  };
 
   // command channel for write/shutdown etc.
   let (kill_tx, kill_rx_read): (Sender<()>, Receiver<()>) = bounded(0);
   let kill_rx_write = kill_rx_read.clone();
 
-  let (write_tx, write_rx): (Sender<Buffer>, Receiver<Buffer>) = unbounded();
+  let (write_tx, write_rx): (Sender<Command>, Receiver<Command>) =
+    match settings.write_queue_capacity {
+      Some(0) => {
+        return Err(napi::Error::from_reason(
+          "writeQueueCapacity must be greater than 0",
+        ))
+      }
+      Some(capacity) => bounded(capacity as usize),
+      None => unbounded(),
+    };
+  let (priority_tx, priority_rx): (Sender<Command>, Receiver<Command>) = unbounded();
+  let (cmd_tx, cmd_rx): (Sender<Command>, Receiver<Command>) = unbounded();
+  // hands reopened write handles from the read thread to the write thread
+  let (port_tx, port_rx): (Sender<NativePort>, Receiver<NativePort>) = unbounded();
 
-  let on_error = Arc::new(on_error);
-  let read_on_error = on_error.clone();
-  let write_on_error = on_error;
+  // JS reads and writes the handle itself, commands sent anywhere else would go unanswered
+  if settings.manual_mode.unwrap_or(false) {
+    state.running.store(true, Ordering::Relaxed);
+    ACTIVE_PORTS.fetch_add(1, Ordering::Relaxed);
+    return Ok(OpenPort {
+      kill_tx: Some(kill_tx),
+      read_thread: None,
+      write_thread: None,
+      write_tx,
+      priority_tx,
+      cmd_tx,
+      state,
+      origin,
+      pty_slave,
+      readable,
+      writable,
+      shared: None,
+      listener: None,
+      errors,
+      handlers: None,
+      frames: OnceLock::new(),
+      manual: Some(Mutex::new(read_port)),
+    });
+  }
 
-  let read_handle = thread::spawn(move || {
-    loop {
-      crossbeam::select! {
-        // Shutdown requested
-        recv(kill_rx_read) -> _ => break,
-        default() => {
-          let mut buf = [0u8; 1024];
-          match read_port.read(&mut buf) {
-            Ok(n) if n > 0 => {
-              let _ = on_data_received.call(Buffer::from(&buf[..n]), ThreadsafeFunctionCallMode::Blocking);
-            }
-            // zero bytes, continue
-            Ok(_) => continue,
-            // normal: no data this iteration
-            Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
-            // unrecoverable error or port closed -> exit
-            Err(e) => {
-              let _ = read_on_error.call(Err(napi::Error::from_reason(format!("read thread died due to {e}"))), ThreadsafeFunctionCallMode::NonBlocking);
-              break;
-            }
-          }
-        }
-      }
+  // a loopback port has no device that could come back, and only the read thread notices it went away
+  let reconnect = match &origin.target {
+    Target::Device(path) if readable && settings.auto_reconnect.unwrap_or(false) => {
+      Some(Reconnect {
+        line,
+        path: path.clone(),
+        exclusive,
+        initial_rts: settings.initial_rts,
+        one_point_five_stop_bits,
+        stick_parity,
+        os_read_buffer_bytes: settings.os_read_buffer_bytes,
+        low_latency,
+        rs485: settings.rs485.clone(),
+        kernel_rs485: rs485.is_none(),
+        interval: Duration::from_millis(settings.reconnect_interval_ms.unwrap_or(1000) as u64),
+        usb: UsbIdentity::lookup(path),
+        port_tx: writable.then_some(port_tx),
+      })
     }
-  });
+    _ => None,
+  };
 
-  let write_handle = thread::spawn(move || {
-    loop {
-      crossbeam::select! {
-        // Shutdown requested
-        recv(kill_rx_write) -> _ => break,
-        // Write data
-        recv(write_rx) -> msg => {
-          match msg {
-            Ok(data) => {
-              if let Err(e) = write_port.write_all(&data) {
-                let _ = write_on_error.call(Err(napi::Error::from_reason(format!("failed to write: {e}"))), ThreadsafeFunctionCallMode::NonBlocking);
-                continue;
-              }
-            }
-            // channel closed, exit
-            Err(RecvError) => {
-              let _ = write_on_error.call(Err(napi::Error::from_reason(format!("write channel closed?!"))), ThreadsafeFunctionCallMode::NonBlocking);
-              break;
-            }
-          }
-        }
+  // the OS may quietly substitute the closest rate it supports for odd ones,
+  // the master end of a pty has no baud rate of its own to compare though
+  if let (Ok(effective), None) = (read_port.baud_rate(), &pty_slave) {
+    if effective.abs_diff(baud) as u64 * 100 > baud as u64 * BAUD_RATE_TOLERANCE_PERCENT {
+      errors.report(PortError::new(
+        PortErrorKind::BaudRateMismatch,
+        format!("requested {baud} baud but the port is running at {effective} baud"),
+      ));
+    }
+  }
+
+  // only worth an error where the OS could have honoured it
+  if let Some(bytes) = settings.os_read_buffer_bytes {
+    match set_input_buffer_size(&read_port, bytes) {
+      Err(e) if e.kind() != std::io::ErrorKind::Unsupported => errors.report(PortError::from_io(
+        &format!("failed to set the OS input buffer to {bytes} bytes"),
+        &e,
+      )),
+      _ => {}
+    }
+  }
+
+  // a pseudo terminal has no latency timer to turn off
+  if low_latency && matches!(origin.target, Target::Device(_)) {
+    match enable_low_latency(&read_port) {
+      Err(e) if e.kind() != std::io::ErrorKind::Unsupported => {
+        errors.report(PortError::from_io("failed to enable low latency mode", &e))
       }
+      _ => {}
     }
+  }
+
+  // in shared mode every handle gets data as a listener, so the one that opened the device can leave first
+  let shared = matches!(origin.target, Target::Device(_)) && settings.shared.unwrap_or(false);
+  let on_data_received = origin.on_data_received.lock().unwrap().clone();
+  let listener = shared.then(|| state.listeners.add(on_data_received.clone()));
+
+  let discard_after_open = settings
+    .discard_ms_after_open
+    .map(|ms| Duration::from_millis(ms as u64));
+  // a later `reopen` doesn't call it again
+  let on_ready = origin.on_ready.lock().unwrap().take();
+  let (read_ready, write_ready) = match readable {
+    true => (on_ready, None),
+    false => (None, on_ready),
+  };
+
+  // counted since the driver was loaded, only increases from here on are news
+  let overruns = overrun_count(&read_port);
+  let reader = Reader {
+    port: read_port,
+    framer,
+    read_buffer_size,
+    state: state.clone(),
+    on_data_received: (!shared).then_some(on_data_received),
+    call_mode: match settings.callback_mode {
+      Some(CallbackMode::NonBlocking) => ThreadsafeFunctionCallMode::NonBlocking,
+      Some(CallbackMode::Blocking) | None => ThreadsafeFunctionCallMode::Blocking,
+    },
+    thrown: match settings.callback_errors {
+      Some(CallbackErrors::Ignore) => Thrown::Ignore,
+      Some(CallbackErrors::Report) => Thrown::Report(errors.clone()),
+      Some(CallbackErrors::Throw) | None => Thrown::Rethrow,
+    },
+    errors: errors.clone(),
+    reconnect,
+    pending_read: None,
+    liveness_poll: settings
+      .liveness_poll_ms
+      .map(|ms| Duration::from_millis(ms as u64)),
+    coalesce,
+    text: TextDecoder::from_settings(settings),
+    parity_marks,
+    trace: trace.clone(),
+    max_idle_wait: settings
+      .max_idle_poll_ms
+      .map(|ms| Duration::from_millis(ms as u64)),
+    discard_after_open,
+    discard_until: discard_after_open.map(|discard| Instant::now() + discard),
+    frame_txs: Vec::new(),
+    opened_at: settings.timestamp_reads.unwrap_or(false).then(Instant::now),
+    read_at: Instant::now(),
+    echo: echo.clone(),
+    overruns,
+    on_ready: read_ready,
+  };
+  // `reopen` goes back to the framing from the settings
+  *state.encoding.lock().unwrap() = Encoding::from_settings(settings);
+  state.running.store(true, Ordering::Relaxed);
+
+  let writer = Writer {
+    port: write_port,
+    // without a read thread transactions fail rather than wait for a reply forever
+    reader_tx: if readable {
+      cmd_tx.clone()
+    } else {
+      unbounded().0
+    },
+    errors: errors.clone(),
+    state: state.clone(),
+    rs485,
+    write_timeout: settings
+      .write_timeout_ms
+      .map(|ms| Duration::from_millis(ms as u64)),
+    encoding: Encoding::from_settings(settings),
+    close_deadline: None,
+    trace,
+    on_ready: write_ready,
+    chunk_size,
+    priority_rx: priority_rx.clone(),
+    in_priority: false,
+    coalesce: write_coalesce,
+    on_write_progress: origin.on_write_progress.lock().unwrap().clone(),
+    echo,
+  };
+  let write_state = state.clone();
+  let write_handle = writable.then(|| {
+    thread::spawn(move || {
+      writer.run(kill_rx_write, priority_rx, write_rx, port_rx);
+      write_state.running.store(false, Ordering::Relaxed);
+    })
+  });
+
+  // spawned last, so `on_ready` means both threads are up
+  let read_state = state.clone();
+  let read_handle = readable.then(|| {
+    thread::spawn(move || {
+      reader.run(kill_rx_read, cmd_rx);
+      read_state.running.store(false, Ordering::Relaxed);
+    })
   });
 
+  // with a single worker thread every command goes to that one
+  let (write_tx, priority_tx, cmd_tx) = match (readable, writable) {
+    (true, false) => (cmd_tx.clone(), cmd_tx.clone(), cmd_tx),
+    (false, true) => (write_tx.clone(), priority_tx, write_tx),
+    _ => (write_tx, priority_tx, cmd_tx),
+  };
+
+  ACTIVE_PORTS.fetch_add(1, Ordering::Relaxed);
   Ok(OpenPort {
     kill_tx: Some(kill_tx),
-    read_thread: Some(read_handle),
-    write_thread: Some(write_handle),
+    read_thread: read_handle,
+    write_thread: write_handle,
     write_tx,
+    priority_tx,
+    cmd_tx,
+    state,
+    origin,
+    pty_slave,
+    readable,
+    writable,
+    shared: None,
+    listener,
+    errors,
+    handlers: shared.then_some(handlers),
+    frames: OnceLock::new(),
+    manual: None,
   })
 }
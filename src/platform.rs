@@ -0,0 +1,682 @@
+// Platform specific tweaks that `serialport` doesn't expose, applied through the raw OS handle
+
+use crate::open_port::NativePort;
+use crate::types::Rs485Settings;
+
+// RTS level between transmissions, the opposite of the transmit level unless asked for otherwise
+pub(crate) fn bus_is_idle_high(settings: &Rs485Settings) -> bool {
+  settings
+    .bus_is_idle_high
+    .unwrap_or(!settings.rts_on_send.unwrap_or(true))
+}
+
+// Let the kernel drive RTS around each transmission using TIOCSRS485.
+// Fails with ENOTTY when the driver has no RS-485 support.
+#[cfg(target_os = "linux")]
+pub(crate) fn enable_kernel_rs485(
+  port: &NativePort,
+  settings: &Rs485Settings,
+) -> std::io::Result<()> {
+  use std::os::unix::io::AsRawFd;
+
+  // struct serial_rs485 from linux/serial.h
+  #[repr(C)]
+  struct SerialRs485 {
+    flags: u32,
+    delay_rts_before_send: u32,
+    delay_rts_after_send: u32,
+    padding: [u32; 5],
+  }
+
+  const SER_RS485_ENABLED: u32 = 1 << 0;
+  const SER_RS485_RTS_ON_SEND: u32 = 1 << 1;
+  const SER_RS485_RTS_AFTER_SEND: u32 = 1 << 2;
+
+  let mut flags = SER_RS485_ENABLED;
+  if settings.rts_on_send.unwrap_or(true) {
+    flags |= SER_RS485_RTS_ON_SEND;
+  }
+  if bus_is_idle_high(settings) {
+    flags |= SER_RS485_RTS_AFTER_SEND;
+  }
+
+  let conf = SerialRs485 {
+    flags,
+    delay_rts_before_send: 0,
+    // the kernel counts this delay in whole milliseconds
+    delay_rts_after_send: settings.rts_after_send_delay_us.unwrap_or(0).div_ceil(1000),
+    padding: [0; 5],
+  };
+
+  let res = unsafe { libc::ioctl(port.as_raw_fd(), libc::TIOCSRS485 as _, &conf) };
+  if res < 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn enable_kernel_rs485(_: &NativePort, _: &Rs485Settings) -> std::io::Result<()> {
+  Err(std::io::Error::new(
+    std::io::ErrorKind::Unsupported,
+    "kernel RS-485 mode is only available on Linux",
+  ))
+}
+
+// Ask the driver for an input buffer of this size with SetupComm, the output buffer is requested
+// at the same size. Drivers are free to round or ignore the request.
+#[cfg(windows)]
+pub(crate) fn set_input_buffer_size(port: &NativePort, bytes: u32) -> std::io::Result<()> {
+  use std::os::windows::io::AsRawHandle;
+
+  #[link(name = "kernel32")]
+  extern "system" {
+    fn SetupComm(handle: *mut std::ffi::c_void, in_queue: u32, out_queue: u32) -> i32;
+  }
+
+  if unsafe { SetupComm(port.as_raw_handle(), bytes, bytes) } == 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(())
+}
+
+// struct DCB from winbase.h, the bit fields between BaudRate and wReserved are packed into one word
+#[cfg(windows)]
+#[repr(C)]
+#[derive(Default)]
+struct Dcb {
+  length: u32,
+  baud_rate: u32,
+  flags: u32,
+  reserved: u16,
+  xon_limit: u16,
+  xoff_limit: u16,
+  byte_size: u8,
+  parity: u8,
+  stop_bits: u8,
+  xon_char: i8,
+  xoff_char: i8,
+  error_char: i8,
+  eof_char: i8,
+  event_char: i8,
+  reserved1: u16,
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+  fn GetCommState(handle: *mut std::ffi::c_void, dcb: *mut Dcb) -> i32;
+  fn SetCommState(handle: *mut std::ffi::c_void, dcb: *const Dcb) -> i32;
+}
+
+#[cfg(windows)]
+fn comm_state(port: &NativePort) -> std::io::Result<Dcb> {
+  use std::os::windows::io::AsRawHandle;
+
+  let mut dcb = Dcb {
+    length: std::mem::size_of::<Dcb>() as u32,
+    ..Dcb::default()
+  };
+  if unsafe { GetCommState(port.as_raw_handle(), &mut dcb) } == 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(dcb)
+}
+
+// Change the settings `serialport` has no way to express directly in the port's DCB
+#[cfg(windows)]
+fn update_comm_state(port: &NativePort, update: impl FnOnce(&mut Dcb)) -> std::io::Result<()> {
+  use std::os::windows::io::AsRawHandle;
+
+  let mut dcb = comm_state(port)?;
+  update(&mut dcb);
+  if unsafe { SetCommState(port.as_raw_handle(), &dcb) } == 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(())
+}
+
+#[cfg(windows)]
+const ONE5STOPBITS: u8 = 1;
+
+// Send 1.5 stop bits. The port is opened with one, Windows refuses 5 data bits with two.
+#[cfg(windows)]
+pub(crate) fn enable_one_point_five_stop_bits(port: &NativePort) -> std::io::Result<()> {
+  update_comm_state(port, |dcb| dcb.stop_bits = ONE5STOPBITS)
+}
+
+// termios has no 1.5, the port was opened with two stop bits which 5 bit characters turn into 1.5
+#[cfg(unix)]
+pub(crate) fn enable_one_point_five_stop_bits(_: &NativePort) -> std::io::Result<()> {
+  Ok(())
+}
+
+// Whether the port sends 1.5 stop bits, which `serialport` can't report on Windows
+#[cfg(windows)]
+pub(crate) fn has_one_point_five_stop_bits(port: &NativePort) -> bool {
+  comm_state(port).is_ok_and(|dcb| dcb.stop_bits == ONE5STOPBITS)
+}
+
+// Reported as two stop bits with 5 bit characters on Unix
+#[cfg(unix)]
+pub(crate) fn has_one_point_five_stop_bits(_: &NativePort) -> bool {
+  false
+}
+
+// The tty layer sizes its buffers itself, there is nothing to ask for on Unix
+#[cfg(unix)]
+pub(crate) fn set_input_buffer_size(_: &NativePort, _: u32) -> std::io::Result<()> {
+  Err(std::io::Error::new(
+    std::io::ErrorKind::Unsupported,
+    "the OS input buffer size can only be set on Windows",
+  ))
+}
+
+// struct serial_struct from linux/serial.h
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct SerialStruct {
+  kind: libc::c_int,
+  line: libc::c_int,
+  port: libc::c_uint,
+  irq: libc::c_int,
+  flags: libc::c_int,
+  xmit_fifo_size: libc::c_int,
+  custom_divisor: libc::c_int,
+  baud_base: libc::c_int,
+  close_delay: libc::c_ushort,
+  io_type: libc::c_char,
+  reserved_char: [libc::c_char; 1],
+  hub6: libc::c_int,
+  closing_wait: libc::c_ushort,
+  closing_wait2: libc::c_ushort,
+  iomem_base: *mut libc::c_uchar,
+  iomem_reg_shift: libc::c_ushort,
+  port_high: libc::c_uint,
+  iomap_base: libc::c_ulong,
+}
+
+#[cfg(target_os = "linux")]
+const ASYNC_LOW_LATENCY: libc::c_int = 1 << 13;
+
+#[cfg(target_os = "linux")]
+fn serial_info(port: &NativePort) -> std::io::Result<SerialStruct> {
+  use std::os::unix::io::AsRawFd;
+
+  let mut serial: SerialStruct = unsafe { std::mem::zeroed() };
+  if unsafe { libc::ioctl(port.as_raw_fd(), libc::TIOCGSERIAL as _, &mut serial) } < 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(serial)
+}
+
+// Set ASYNC_LOW_LATENCY with TIOCSSERIAL, which has USB adapters like FTDI's hand over received
+// bytes right away instead of after their latency timer (16 ms by default)
+#[cfg(target_os = "linux")]
+pub(crate) fn enable_low_latency(port: &NativePort) -> std::io::Result<()> {
+  use std::os::unix::io::AsRawFd;
+
+  let mut serial = serial_info(port)?;
+  serial.flags |= ASYNC_LOW_LATENCY;
+  if unsafe { libc::ioctl(port.as_raw_fd(), libc::TIOCSSERIAL as _, &serial) } < 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn enable_low_latency(_: &NativePort) -> std::io::Result<()> {
+  Err(std::io::Error::new(
+    std::io::ErrorKind::Unsupported,
+    "low latency mode is only available on Linux",
+  ))
+}
+
+// Whether ASYNC_LOW_LATENCY is set, None where the driver can't be asked
+#[cfg(target_os = "linux")]
+pub(crate) fn has_low_latency(port: &NativePort) -> Option<bool> {
+  serial_info(port)
+    .ok()
+    .map(|serial| serial.flags & ASYNC_LOW_LATENCY != 0)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn has_low_latency(_: &NativePort) -> Option<bool> {
+  None
+}
+
+// Receive overruns the driver counted since it was loaded, from TIOCGICOUNT: bytes lost because
+// the UART's FIFO or the tty buffer was full. None when the driver keeps no counters.
+#[cfg(target_os = "linux")]
+pub(crate) fn overrun_count(port: &NativePort) -> Option<u64> {
+  use std::os::unix::io::AsRawFd;
+
+  // struct serial_icounter_struct from linux/serial.h
+  #[repr(C)]
+  struct SerialIcounter {
+    cts: libc::c_int,
+    dsr: libc::c_int,
+    rng: libc::c_int,
+    dcd: libc::c_int,
+    rx: libc::c_int,
+    tx: libc::c_int,
+    frame: libc::c_int,
+    overrun: libc::c_int,
+    parity: libc::c_int,
+    brk: libc::c_int,
+    buf_overrun: libc::c_int,
+    reserved: [libc::c_int; 9],
+  }
+
+  let mut counters: SerialIcounter = unsafe { std::mem::zeroed() };
+  if unsafe { libc::ioctl(port.as_raw_fd(), libc::TIOCGICOUNT as _, &mut counters) } < 0 {
+    return None;
+  }
+
+  Some(counters.overrun as u32 as u64 + counters.buf_overrun as u32 as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn overrun_count(_: &NativePort) -> Option<u64> {
+  None
+}
+
+// The OS handle behind a port: a file descriptor on Unix
+#[cfg(unix)]
+pub(crate) fn raw_handle(port: &NativePort) -> i64 {
+  use std::os::unix::io::AsRawFd;
+
+  port.as_raw_fd() as i64
+}
+
+// The OS handle behind a port: a HANDLE on Windows
+#[cfg(windows)]
+pub(crate) fn raw_handle(port: &NativePort) -> i64 {
+  use std::os::windows::io::AsRawHandle;
+
+  port.as_raw_handle() as isize as i64
+}
+
+// Have the OS flag bytes received with a parity or framing error by prefixing them
+// with 0xFF 0x00, a genuine 0xFF byte then arrives doubled
+#[cfg(unix)]
+pub(crate) fn enable_parity_marking(port: &NativePort) -> std::io::Result<()> {
+  use std::os::unix::io::AsRawFd;
+
+  let fd = port.as_raw_fd();
+  let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+  if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  termios.c_iflag |= libc::INPCK | libc::PARMRK;
+  termios.c_iflag &= !(libc::IGNPAR | libc::ISTRIP);
+  if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(())
+}
+
+#[cfg(windows)]
+pub(crate) fn enable_parity_marking(_: &NativePort) -> std::io::Result<()> {
+  Err(std::io::Error::new(
+    std::io::ErrorKind::Unsupported,
+    "parity error reporting is only available on Unix",
+  ))
+}
+
+// Switch odd/even parity to mark/space ("stick") parity: the parity bit is always 1 for mark, 0 for space.
+// The PARODD flag the port was opened with picks which one.
+#[cfg(target_os = "linux")]
+pub(crate) fn enable_stick_parity(port: &NativePort) -> std::io::Result<()> {
+  use std::os::unix::io::AsRawFd;
+
+  let fd = port.as_raw_fd();
+  let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+  if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  termios.c_cflag |= libc::PARENB | libc::CMSPAR;
+  if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(())
+}
+
+#[cfg(windows)]
+const ODDPARITY: u8 = 1;
+#[cfg(windows)]
+const MARKPARITY: u8 = 3;
+#[cfg(windows)]
+const SPACEPARITY: u8 = 4;
+
+// Windows has mark and space parity settings of their own, picked by the odd/even parity the port was opened with
+#[cfg(windows)]
+pub(crate) fn enable_stick_parity(port: &NativePort) -> std::io::Result<()> {
+  update_comm_state(port, |dcb| {
+    dcb.parity = if dcb.parity == ODDPARITY {
+      MARKPARITY
+    } else {
+      SPACEPARITY
+    };
+  })
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) fn enable_stick_parity(_: &NativePort) -> std::io::Result<()> {
+  Err(std::io::Error::new(
+    std::io::ErrorKind::Unsupported,
+    "mark and space parity are only available on Linux and Windows",
+  ))
+}
+
+// Pick mark (true) or space parity on a port already set up for stick parity, once the output queued
+// so far has been sent. Returns which of the two was in effect before.
+#[cfg(target_os = "linux")]
+pub(crate) fn set_mark_parity(port: &NativePort, mark: bool) -> std::io::Result<bool> {
+  use std::os::unix::io::AsRawFd;
+
+  let fd = port.as_raw_fd();
+  let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+  if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  if termios.c_cflag & libc::CMSPAR == 0 {
+    return Err(std::io::Error::new(
+      std::io::ErrorKind::InvalidInput,
+      "the port isn't using mark or space parity",
+    ));
+  }
+
+  let previous = termios.c_cflag & libc::PARODD != 0;
+  if previous == mark {
+    return Ok(previous);
+  }
+
+  if mark {
+    termios.c_cflag |= libc::PARODD;
+  } else {
+    termios.c_cflag &= !libc::PARODD;
+  }
+  if unsafe { libc::tcsetattr(fd, libc::TCSADRAIN, &termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(previous)
+}
+
+// FlushFileBuffers waits for the output to be sent, like TCSADRAIN
+#[cfg(windows)]
+pub(crate) fn set_mark_parity(port: &NativePort, mark: bool) -> std::io::Result<bool> {
+  use std::os::windows::io::AsRawHandle;
+
+  #[link(name = "kernel32")]
+  extern "system" {
+    fn FlushFileBuffers(handle: *mut std::ffi::c_void) -> i32;
+  }
+
+  let previous = match comm_state(port)?.parity {
+    MARKPARITY => true,
+    SPACEPARITY => false,
+    _ => {
+      return Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "the port isn't using mark or space parity",
+      ))
+    }
+  };
+  if previous == mark {
+    return Ok(previous);
+  }
+
+  if unsafe { FlushFileBuffers(port.as_raw_handle()) } == 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  update_comm_state(port, |dcb| {
+    dcb.parity = if mark { MARKPARITY } else { SPACEPARITY };
+  })?;
+
+  Ok(previous)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) fn set_mark_parity(_: &NativePort, _: bool) -> std::io::Result<bool> {
+  Err(std::io::Error::new(
+    std::io::ErrorKind::Unsupported,
+    "mark and space parity are only available on Linux and Windows",
+  ))
+}
+
+// Mark (true) or space parity if the port uses one of them, `serialport` only knows odd and even
+#[cfg(target_os = "linux")]
+pub(crate) fn stick_parity(port: &NativePort) -> Option<bool> {
+  use std::os::unix::io::AsRawFd;
+
+  let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+  if unsafe { libc::tcgetattr(port.as_raw_fd(), &mut termios) } != 0 {
+    return None;
+  }
+
+  let stick = termios.c_cflag & (libc::PARENB | libc::CMSPAR) == libc::PARENB | libc::CMSPAR;
+  stick.then_some(termios.c_cflag & libc::PARODD != 0)
+}
+
+#[cfg(windows)]
+pub(crate) fn stick_parity(port: &NativePort) -> Option<bool> {
+  match comm_state(port).ok()?.parity {
+    MARKPARITY => Some(true),
+    SPACEPARITY => Some(false),
+    _ => None,
+  }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) fn stick_parity(_: &NativePort) -> Option<bool> {
+  None
+}
+
+// The /dev/serial/by-id link udev created for `path`, which names the device rather than where it is plugged in
+#[cfg(target_os = "linux")]
+pub(crate) fn by_id_link(path: &str) -> Option<String> {
+  serial_link("/dev/serial/by-id", path)
+}
+
+// The /dev/serial/by-path link udev created for `path`, which names the USB port or bus slot the device is on
+#[cfg(target_os = "linux")]
+pub(crate) fn by_path_link(path: &str) -> Option<String> {
+  serial_link("/dev/serial/by-path", path)
+}
+
+// The link in `dir` that resolves to the same device as `path`
+#[cfg(target_os = "linux")]
+fn serial_link(dir: &str, path: &str) -> Option<String> {
+  let target = std::fs::canonicalize(path).ok()?;
+  std::fs::read_dir(dir)
+    .ok()?
+    .filter_map(Result::ok)
+    .map(|entry| entry.path())
+    .find(|link| std::fs::canonicalize(link).is_ok_and(|dest| dest == target))
+    .map(|link| link.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn by_id_link(_: &str) -> Option<String> {
+  None
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn by_path_link(_: &str) -> Option<String> {
+  None
+}
+
+// Baud rates reported when the driver can't be asked
+pub(crate) const STANDARD_BAUD_RATES: &[u32] = &[
+  300, 600, 1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600,
+];
+
+// Whether `path` is a terminal, and which standard baud rates its driver accepts.
+// The device is never opened: opening a tty raises DTR and closing it drops DTR again (HUPCL), which resets many
+// boards, and trying rates through termios would retune a line another process is using. Only serial core UARTs
+// tell their clock through sysfs, which caps the rate at a sixteenth of it; the file is usually readable by root only.
+#[cfg(target_os = "linux")]
+pub(crate) fn probe_tty(path: &str) -> (Option<bool>, Option<Vec<u32>>) {
+  let Some(name) = tty_name(path) else {
+    // unknown if the path isn't there at all
+    return (std::path::Path::new(path).exists().then_some(false), None);
+  };
+
+  let uartclk = std::fs::read_to_string(format!("/sys/class/tty/{name}/uartclk"))
+    .ok()
+    .and_then(|clock| clock.trim().parse::<u32>().ok())
+    .filter(|&clock| clock > 0);
+  let rates = uartclk.map(|clock| {
+    STANDARD_BAUD_RATES
+      .iter()
+      .copied()
+      .filter(|&rate| rate <= clock / 16)
+      .collect()
+  });
+
+  (Some(true), rates)
+}
+
+// The name the tty layer registered the device under, following links such as /dev/serial/by-id
+#[cfg(target_os = "linux")]
+fn tty_name(path: &str) -> Option<String> {
+  let path = std::fs::canonicalize(path).ok()?;
+  let name = path.file_name()?.to_str()?;
+  std::path::Path::new("/sys/class/tty")
+    .join(name)
+    .exists()
+    .then(|| name.to_string())
+}
+
+// Other platforms can only tell whether the path is a terminal
+#[cfg(all(unix, not(target_os = "linux")))]
+pub(crate) fn probe_tty(path: &str) -> (Option<bool>, Option<Vec<u32>>) {
+  (is_char_device(path), None)
+}
+
+// Checked on the device node alone, for the same reasons `probe_tty` doesn't open it
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_char_device(path: &str) -> Option<bool> {
+  use std::os::unix::fs::FileTypeExt;
+
+  std::fs::metadata(path)
+    .ok()
+    .map(|metadata| metadata.file_type().is_char_device())
+}
+
+#[cfg(windows)]
+pub(crate) fn probe_tty(_: &str) -> (Option<bool>, Option<Vec<u32>>) {
+  (None, None)
+}
+
+// Open a device for reading or writing only, so a read-only device node or a user allowed just one
+// direction can still use it. Set up for raw access the way `open_native` does, the line settings are
+// applied by the caller.
+#[cfg(unix)]
+pub(crate) fn open_one_way(path: &str, writable: bool) -> std::io::Result<NativePort> {
+  use std::os::unix::io::FromRawFd;
+
+  let c_path = std::ffi::CString::new(path)
+    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+  let direction = if writable {
+    libc::O_WRONLY
+  } else {
+    libc::O_RDONLY
+  };
+  let fd = unsafe {
+    libc::open(
+      c_path.as_ptr(),
+      direction | libc::O_NOCTTY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+    )
+  };
+  if fd < 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  // the port owns the descriptor from here on, so it's closed if anything below fails.
+  // This locks the port, the caller sets the requested exclusivity afterwards
+  let port = unsafe { NativePort::from_raw_fd(fd) };
+
+  let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+  if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  termios.c_cflag |= libc::CREAD | libc::CLOCAL;
+  unsafe { libc::cfmakeraw(&mut termios) };
+  if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  // reads and writes block up to the port's timeout from here on
+  if unsafe { libc::fcntl(fd, libc::F_SETFL, 0) } != 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(port)
+}
+
+#[cfg(windows)]
+pub(crate) fn open_one_way(path: &str, writable: bool) -> std::io::Result<NativePort> {
+  use std::os::windows::io::FromRawHandle;
+
+  const GENERIC_READ: u32 = 0x8000_0000;
+  const GENERIC_WRITE: u32 = 0x4000_0000;
+  const OPEN_EXISTING: u32 = 3;
+  const FILE_ATTRIBUTE_NORMAL: u32 = 0x80;
+  const INVALID_HANDLE_VALUE: isize = -1;
+
+  #[link(name = "kernel32")]
+  extern "system" {
+    fn CreateFileW(
+      name: *const u16,
+      access: u32,
+      share_mode: u32,
+      security_attributes: *mut std::ffi::c_void,
+      creation_disposition: u32,
+      flags_and_attributes: u32,
+      template: *mut std::ffi::c_void,
+    ) -> *mut std::ffi::c_void;
+  }
+
+  // COM10 and up are only reachable through the device namespace, same as `open_native`
+  let prefix = if path.starts_with('\\') { "" } else { r"\\.\" };
+  let name: Vec<u16> = prefix
+    .encode_utf16()
+    .chain(path.encode_utf16())
+    .chain(Some(0))
+    .collect();
+  let access = if writable {
+    GENERIC_WRITE
+  } else {
+    GENERIC_READ
+  };
+  let handle = unsafe {
+    CreateFileW(
+      name.as_ptr(),
+      access,
+      0,
+      std::ptr::null_mut(),
+      OPEN_EXISTING,
+      FILE_ATTRIBUTE_NORMAL,
+      std::ptr::null_mut(),
+    )
+  };
+  if handle as isize == INVALID_HANDLE_VALUE {
+    return Err(std::io::Error::last_os_error());
+  }
+
+  Ok(unsafe { NativePort::from_raw_handle(handle) })
+}
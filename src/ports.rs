@@ -1,7 +1,12 @@
-use crate::open_port::open_port;
-use crate::types::{PortSettings, UsbInfo};
-use napi::bindgen_prelude::Buffer;
+use crate::error::Coded;
+use crate::open_port::{
+  open_loopback_port, open_port, open_port_async, open_pty_port, Data, OpenTask,
+};
+use crate::platform::{by_id_link, by_path_link, probe_tty, STANDARD_BAUD_RATES};
+use crate::types::{PortCapabilities, PortError, PortFilter, PortKind, PortSettings, UsbInfo};
+use napi::bindgen_prelude::AsyncTask;
 use napi::threadsafe_function::ThreadsafeFunction;
+use napi::Env;
 use napi_derive::napi;
 use serialport::{SerialPortInfo, SerialPortType};
 
@@ -13,30 +18,310 @@ pub struct AvailablePort {
   pub port_type: String,
   #[napi(readonly, js_name = "usb")]
   pub usb_info: Option<UsbInfo>,
+  /// the /dev/serial/by-id link to the device, Linux only
+  #[napi(readonly)]
+  pub by_id_path: Option<String>,
+  /// the /dev/serial/by-path link to the device, Linux only
+  #[napi(readonly)]
+  pub by_path_path: Option<String>,
 }
 
 #[napi]
 impl AvailablePort {
+  // the `Env` isn't an argument in JS
+  #[allow(clippy::too_many_arguments)]
   #[napi]
   pub fn open(
     &self,
-    on_data_received: ThreadsafeFunction<Buffer, (), Buffer, napi::Status, false>,
-    on_error: ThreadsafeFunction<(), ()>,
+    env: &Env,
+    on_data_received: ThreadsafeFunction<Data, (), Data, napi::Status, false>,
+    on_error: ThreadsafeFunction<PortError, (), PortError, napi::Status, false>,
     settings: Option<PortSettings>,
+    on_disconnect: Option<ThreadsafeFunction<PortError, (), PortError, napi::Status, false>>,
+    on_reconnect: Option<ThreadsafeFunction<String, (), String, napi::Status, false>>,
+    on_ready: Option<ThreadsafeFunction<(), (), (), napi::Status, false>>,
   ) -> napi::Result<crate::open_port::OpenPort> {
-    open_port(&self.path, on_data_received, on_error, settings)
+    open_port(
+      &self.path,
+      on_data_received,
+      on_error,
+      settings,
+      on_disconnect,
+      on_reconnect,
+      on_ready,
+    )
+    .coded(env)
+  }
+
+  /// Like `open`, but opens the port off the JS thread, so several ports can be opened at once.
+  #[napi(ts_return_type = "Promise<OpenPort>")]
+  pub fn open_async(
+    &self,
+    on_data_received: ThreadsafeFunction<Data, (), Data, napi::Status, false>,
+    on_error: ThreadsafeFunction<PortError, (), PortError, napi::Status, false>,
+    settings: Option<PortSettings>,
+    on_disconnect: Option<ThreadsafeFunction<PortError, (), PortError, napi::Status, false>>,
+    on_reconnect: Option<ThreadsafeFunction<String, (), String, napi::Status, false>>,
+    on_ready: Option<ThreadsafeFunction<(), (), (), napi::Status, false>>,
+  ) -> AsyncTask<OpenTask> {
+    AsyncTask::new(open_port_async(
+      &self.path,
+      on_data_received,
+      on_error,
+      settings,
+      on_disconnect,
+      on_reconnect,
+      on_ready,
+    ))
+  }
+
+  /// An id for the device that survives reboots and replugging, unlike the path.
+  ///
+  /// Built from the VID, PID and serial number of USB devices that have one, otherwise the
+  /// `/dev/serial/by-id` link on Linux. None when the device can't be told apart from others.
+  #[napi]
+  pub fn unique_id(&self) -> Option<String> {
+    if let Some(UsbInfo {
+      vid,
+      pid,
+      serial: Some(serial),
+      ..
+    }) = &self.usb_info
+    {
+      return Some(format!("usb:{vid:04x}:{pid:04x}:{serial}"));
+    }
+
+    self.by_id_path.clone()
+  }
+
+  /// A label to show users, e.g. "Arduino LLC Arduino Uno (8573531303)" instead of the path.
+  ///
+  /// Made up of the USB manufacturer, product and serial number where known, otherwise the path. Two
+  /// identical devices without serial numbers get the same name.
+  #[napi]
+  pub fn display_name(&self) -> String {
+    let Some(usb) = &self.usb_info else {
+      return self.path.clone();
+    };
+
+    fn known(s: &Option<String>) -> Option<&str> {
+      s.as_deref().map(str::trim).filter(|s| !s.is_empty())
+    }
+
+    let label = match (known(&usb.manufacturer), known(&usb.product)) {
+      // many products already lead with the manufacturer
+      (Some(manufacturer), Some(product)) if product.starts_with(manufacturer) => {
+        product.to_string()
+      }
+      (Some(manufacturer), Some(product)) => format!("{manufacturer} {product}"),
+      (Some(label), None) | (None, Some(label)) => label.to_string(),
+      (None, None) => return self.path.clone(),
+    };
+
+    match known(&usb.serial) {
+      Some(serial) => format!("{label} ({serial})"),
+      None => label,
+    }
+  }
+
+  /// Find out what the port supports, without opening it
+  #[napi]
+  pub fn capabilities(&self) -> PortCapabilities {
+    let (is_tty, baud_rates) = probe_tty(&self.path);
+    PortCapabilities {
+      port_type: self.port_type.clone(),
+      is_tty,
+      baud_rates_queried: baud_rates.is_some(),
+      baud_rates: baud_rates.unwrap_or_else(|| STANDARD_BAUD_RATES.to_vec()),
+    }
   }
 }
 
 #[napi]
-pub fn list_ports() -> napi::Result<Vec<AvailablePort>> {
+pub fn list_ports(env: &Env) -> napi::Result<Vec<AvailablePort>> {
+  all_ports().coded(env)
+}
+
+fn all_ports() -> napi::Result<Vec<AvailablePort>> {
   let ports = serialport::available_ports()
-    .map_err(|e| napi::Error::from_reason(format!("list_ports failed: {}", e)))?;
+    .map_err(|e| PortError::from_serialport("list_ports failed", &e))?;
 
   Ok(ports.into_iter().map(serial_info_to_port).collect())
 }
 
-fn serial_info_to_port(p: SerialPortInfo) -> AvailablePort {
+/// Open the USB port whose serial number is `serial`, wherever it is currently attached
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub fn open_by_serial(
+  env: &Env,
+  serial: String,
+  on_data_received: ThreadsafeFunction<Data, (), Data, napi::Status, false>,
+  on_error: ThreadsafeFunction<PortError, (), PortError, napi::Status, false>,
+  settings: Option<PortSettings>,
+  on_disconnect: Option<ThreadsafeFunction<PortError, (), PortError, napi::Status, false>>,
+  on_reconnect: Option<ThreadsafeFunction<String, (), String, napi::Status, false>>,
+  on_ready: Option<ThreadsafeFunction<(), (), (), napi::Status, false>>,
+) -> napi::Result<crate::open_port::OpenPort> {
+  let matches = all_ports()
+    .coded(env)?
+    .into_iter()
+    .filter(|p| p.usb_info.as_ref().and_then(|usb| usb.serial.as_ref()) == Some(&serial));
+  let port = only_match(matches, &format!("USB port with serial number {serial}"))?;
+
+  open_port(
+    &port.path,
+    on_data_received,
+    on_error,
+    settings,
+    on_disconnect,
+    on_reconnect,
+    on_ready,
+  )
+  .coded(env)
+}
+
+/// Open the port whose `displayName()` is `name`, e.g. the one a user picked from a list.
+///
+/// Fails rather than guessing when several ports share the name.
+#[allow(clippy::too_many_arguments)]
+#[napi]
+pub fn open_by_display_name(
+  env: &Env,
+  name: String,
+  on_data_received: ThreadsafeFunction<Data, (), Data, napi::Status, false>,
+  on_error: ThreadsafeFunction<PortError, (), PortError, napi::Status, false>,
+  settings: Option<PortSettings>,
+  on_disconnect: Option<ThreadsafeFunction<PortError, (), PortError, napi::Status, false>>,
+  on_reconnect: Option<ThreadsafeFunction<String, (), String, napi::Status, false>>,
+  on_ready: Option<ThreadsafeFunction<(), (), (), napi::Status, false>>,
+) -> napi::Result<crate::open_port::OpenPort> {
+  let matches = all_ports()
+    .coded(env)?
+    .into_iter()
+    .filter(|p| p.display_name() == name);
+  let port = only_match(matches, &format!("port named {name}"))?;
+
+  open_port(
+    &port.path,
+    on_data_received,
+    on_error,
+    settings,
+    on_disconnect,
+    on_reconnect,
+    on_ready,
+  )
+  .coded(env)
+}
+
+// The single port in `matches`, failing when there is none or more than one
+fn only_match(
+  mut matches: impl Iterator<Item = AvailablePort>,
+  what: &str,
+) -> napi::Result<AvailablePort> {
+  match (matches.next(), matches.next()) {
+    (Some(port), None) => Ok(port),
+    (None, _) => Err(napi::Error::from_reason(format!("no {what} found"))),
+    (Some(first), Some(second)) => {
+      let paths: Vec<String> = [first, second]
+        .into_iter()
+        .chain(matches)
+        .map(|p| p.path)
+        .collect();
+      Err(napi::Error::from_reason(format!(
+        "more than one {what} found: {}",
+        paths.join(", ")
+      )))
+    }
+  }
+}
+
+/// Open a port that echoes every write straight back, for testing without hardware.
+///
+/// Writes go through the usual framing before reaching `on_data_received`.
+#[napi]
+pub fn open_loopback(
+  env: &Env,
+  on_data_received: ThreadsafeFunction<Data, (), Data, napi::Status, false>,
+  on_error: ThreadsafeFunction<PortError, (), PortError, napi::Status, false>,
+  settings: Option<PortSettings>,
+) -> napi::Result<crate::open_port::OpenPort> {
+  open_loopback_port(on_data_received, on_error, settings).coded(env)
+}
+
+/// Create a pseudo terminal and open its master end, for testing code that opens a serial port by path.
+///
+/// The returned port's `path` is the other end, whatever is written to one end can be read from the other.
+#[napi]
+pub fn open_pty_pair(
+  env: &Env,
+  on_data_received: ThreadsafeFunction<Data, (), Data, napi::Status, false>,
+  on_error: ThreadsafeFunction<PortError, (), PortError, napi::Status, false>,
+  settings: Option<PortSettings>,
+) -> napi::Result<crate::open_port::OpenPort> {
+  open_pty_port(on_data_received, on_error, settings).coded(env)
+}
+
+/// List the available ports matching `filter`, any USB criteria only match USB ports
+#[napi]
+pub fn list_ports_filtered(env: &Env, filter: PortFilter) -> napi::Result<Vec<AvailablePort>> {
+  Ok(
+    all_ports()
+      .coded(env)?
+      .into_iter()
+      .filter(|p| filter.matches(p))
+      .collect(),
+  )
+}
+
+/// List the available ports of one kind, e.g. only USB adapters
+#[napi]
+pub fn list_ports_by_type(env: &Env, kind: PortKind) -> napi::Result<Vec<AvailablePort>> {
+  let wanted = match kind {
+    PortKind::Usb => "Usb",
+    PortKind::Bluetooth => "Bluetooth",
+    PortKind::Pci => "Pci",
+    PortKind::Unknown => "Unknown",
+  };
+
+  Ok(
+    all_ports()
+      .coded(env)?
+      .into_iter()
+      .filter(|p| p.port_type == wanted)
+      .collect(),
+  )
+}
+
+impl PortFilter {
+  fn matches(&self, port: &AvailablePort) -> bool {
+    let wants_usb = self.vid.is_some()
+      || self.pid.is_some()
+      || self.serial.is_some()
+      || self.manufacturer_contains.is_some();
+    if !wants_usb {
+      return true;
+    }
+
+    let Some(usb) = &port.usb_info else {
+      return false;
+    };
+
+    self.vid.is_none_or(|vid| vid == usb.vid)
+      && self.pid.is_none_or(|pid| pid == usb.pid)
+      && self
+        .serial
+        .as_ref()
+        .is_none_or(|serial| usb.serial.as_ref() == Some(serial))
+      && self.manufacturer_contains.as_ref().is_none_or(|needle| {
+        usb
+          .manufacturer
+          .as_ref()
+          .is_some_and(|m| m.contains(needle.as_str()))
+      })
+  }
+}
+
+pub(crate) fn serial_info_to_port(p: SerialPortInfo) -> AvailablePort {
   let (port_type, usb_info) = match p.port_type {
     SerialPortType::UsbPort(info) => {
       let usb_info = UsbInfo {
@@ -54,6 +339,8 @@ fn serial_info_to_port(p: SerialPortInfo) -> AvailablePort {
   };
 
   AvailablePort {
+    by_id_path: by_id_link(&p.port_name),
+    by_path_path: by_path_link(&p.port_name),
     path: p.port_name,
     port_type,
     usb_info,
@@ -0,0 +1,901 @@
+// The read thread: pulls bytes off the port, frames them and hands them to JS
+
+use napi::bindgen_prelude::{Buffer, Either, Either3};
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
+
+use serialport::{SerialPort, SerialPortType};
+
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{Receiver, Sender, TryRecvError};
+
+use crate::echo::LocalEcho;
+use crate::error::{is_disconnect, ErrorSink, Thrown};
+use crate::frames::{FrameSender, Pushed};
+use crate::framing::{FrameEvent, Framer, TextDecoder};
+use crate::open_port::{
+  execute, open_native_pair, Data, LineSettings, NativePort, OnDataReceivedCallback,
+  OnReadyCallback, SharedState,
+};
+use crate::platform::{
+  bus_is_idle_high, enable_kernel_rs485, enable_low_latency, enable_one_point_five_stop_bits,
+  enable_parity_marking, enable_stick_parity, overrun_count, set_input_buffer_size,
+};
+use crate::trace::{Direction, Trace};
+use crate::types::{
+  Command, PortError, PortErrorKind, ReadRequest, ReadUntil, Reply, Rs485Settings, TimestampedData,
+};
+
+// How often a paused read thread checks whether it was resumed
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+// Data callbacks JS may fall behind on before non-blocking delivery starts dropping frames
+const MAX_PENDING_CALLBACKS: usize = 256;
+
+// How often the driver's overrun counter is checked
+const OVERRUN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// First wait between polls once a port goes quiet, doubled on every empty read
+const IDLE_BACKOFF_START: Duration = Duration::from_millis(1);
+
+pub(crate) struct Reader {
+  pub port: NativePort,
+  pub framer: Framer,
+  pub read_buffer_size: usize,
+  pub state: Arc<SharedState>,
+  // unset in shared mode, where every handle's callback is a listener
+  pub on_data_received: Option<Arc<OnDataReceivedCallback>>,
+  // how data callbacks are queued, non-blocking ones are dropped when JS falls behind
+  pub call_mode: ThreadsafeFunctionCallMode,
+  // what becomes of an error a data callback throws
+  pub thrown: Thrown,
+  // also hears about disconnects and reconnects
+  pub errors: Arc<ErrorSink>,
+  // set when the port should be reopened after the device goes away
+  pub reconnect: Option<Reconnect>,
+  // a one-shot read waiting for data
+  pub pending_read: Option<ReadRequest>,
+  // how often to check the device is still there, for drivers that go silent instead of failing reads
+  pub liveness_poll: Option<Duration>,
+  // set when small reads should be held back and delivered together
+  pub coalesce: Option<Coalesce>,
+  // set when frames are delivered as text
+  pub text: Option<TextDecoder>,
+  // set when the OS marks bytes that arrived with a parity or framing error
+  pub parity_marks: Option<ParityMarks>,
+  pub trace: Option<Arc<Trace>>,
+  // overruns the driver counted so far, None when it doesn't count them
+  pub overruns: Option<u64>,
+  // longest wait between polls of a quiet port, no backoff when unset
+  pub max_idle_wait: Option<Duration>,
+  // how long to drop everything read after (re)opening, e.g. a firmware banner
+  pub discard_after_open: Option<Duration>,
+  pub discard_until: Option<Instant>,
+  // queues `next_frame` takes frames from, one per subscribed handle
+  pub frame_txs: Vec<FrameSender>,
+  // set when frames are delivered with the time they were read, counted from here
+  pub opened_at: Option<Instant>,
+  // when the last read that returned data did, the timestamp of any frame it completes
+  pub read_at: Instant,
+  // set when the echo of what the write thread sent is dropped
+  pub echo: Option<Arc<LocalEcho>>,
+  // called as the first read loop starts
+  pub on_ready: Option<OnReadyCallback>,
+}
+
+pub(crate) struct Coalesce {
+  // deliver once this many bytes are held
+  pub min_bytes: usize,
+  // or once the oldest held byte has waited this long
+  pub timeout: Duration,
+  pub held: Vec<u8>,
+  pub since: Instant,
+}
+
+// Strips the markers the OS puts around bytes that arrived with a parity or framing error
+#[derive(Default)]
+pub(crate) struct ParityMarks {
+  // how far into the marker sequence the last read ended
+  pending: usize,
+  // position in the cleaned stream, for the error message
+  offset: u64,
+}
+
+// What stripping the markers from a read turned up
+enum Mark {
+  // a byte that arrived with a parity or framing error, kept in the data
+  Error(u64),
+  // a break condition, which carries no data
+  Break(u64),
+}
+
+impl ParityMarks {
+  // Remove the markers from `data`, a sequence split across reads is completed by the next one
+  fn strip(&mut self, data: &[u8]) -> (Vec<u8>, Vec<Mark>) {
+    let mut out = Vec::with_capacity(data.len());
+    let mut marks = Vec::new();
+
+    for &b in data {
+      match (self.pending, b) {
+        (0, 0xFF) => self.pending = 1,
+        (0, b) => out.push(b),
+        // an escaped 0xFF
+        (1, 0xFF) => {
+          out.push(0xFF);
+          self.pending = 0;
+        }
+        (1, 0x00) => self.pending = 2,
+        // not a marker after all, which PARMRK never produces but is best passed on as-is
+        (1, b) => {
+          out.extend_from_slice(&[0xFF, b]);
+          self.pending = 0;
+        }
+        (_, 0x00) => {
+          marks.push(Mark::Break(self.offset + out.len() as u64));
+          self.pending = 0;
+        }
+        (_, b) => {
+          marks.push(Mark::Error(self.offset + out.len() as u64));
+          out.push(b);
+          self.pending = 0;
+        }
+      }
+    }
+
+    self.offset += out.len() as u64;
+    (out, marks)
+  }
+}
+
+// Everything needed to reopen the device after it went away
+pub(crate) struct Reconnect {
+  pub line: LineSettings,
+  pub path: String,
+  pub exclusive: bool,
+  pub initial_rts: Option<bool>,
+  // 1.5 stop bits and mark or space parity have to be set up again on every new handle
+  pub one_point_five_stop_bits: bool,
+  pub stick_parity: bool,
+  pub os_read_buffer_bytes: Option<u32>,
+  pub low_latency: bool,
+  // RS-485 is set up again the same way, by the kernel if it took it at open or else by parking RTS
+  pub rs485: Option<Rs485Settings>,
+  pub kernel_rs485: bool,
+  pub interval: Duration,
+  // USB identity of the device, used to find it again if it comes back under another path
+  pub usb: Option<UsbIdentity>,
+  // hands the new write handle over to the write thread, if there is one
+  pub port_tx: Option<Sender<NativePort>>,
+}
+
+pub(crate) struct UsbIdentity {
+  pub vid: u16,
+  pub pid: u16,
+  pub serial: String,
+}
+
+impl UsbIdentity {
+  // Identify the device behind `path`, only USB devices with a serial number are identifiable
+  pub fn lookup(path: &str) -> Option<Self> {
+    serialport::available_ports()
+      .ok()?
+      .into_iter()
+      .find(|p| p.port_name == path)
+      .and_then(|p| match p.port_type {
+        SerialPortType::UsbPort(info) => Some(UsbIdentity {
+          vid: info.vid,
+          pid: info.pid,
+          serial: info.serial_number?,
+        }),
+        _ => None,
+      })
+  }
+}
+
+impl Reader {
+  pub fn run(mut self, kill_rx: Receiver<()>, cmd_rx: Receiver<Command>) {
+    let mut buf = vec![0u8; self.read_buffer_size];
+    let mut last_poll = Instant::now();
+    let mut last_overrun_check = Instant::now();
+    let mut last_data = Instant::now();
+    // how long to wait for a command before polling the port again
+    let mut idle_wait = Duration::ZERO;
+    if let Some(on_ready) = self.on_ready.take() {
+      let _ = on_ready.call((), ThreadsafeFunctionCallMode::NonBlocking);
+    }
+    loop {
+      crossbeam::select! {
+        // Shutdown requested
+        recv(kill_rx) -> _ => break,
+        // Reconfigure or query the port
+        recv(cmd_rx) -> cmd => {
+          // someone is interested in the port again
+          idle_wait = Duration::ZERO;
+          match cmd {
+            Ok(Command::ReadOnce(request)) => self.start_read(request),
+            Ok(Command::SetTimeout(timeout, reply)) => self.set_timeout(timeout, reply),
+            Ok(Command::SetFlowControl(flow_control, reply)) => {
+              self.set_flow_control(flow_control, reply)
+            }
+            Ok(Command::SubscribeFrames(frame_tx)) => self.frame_txs.push(frame_tx),
+            Ok(Command::SetFramer(framer, reply)) => self.set_framer(*framer, reply),
+            Ok(Command::ResetFrameBuffer(reply)) => {
+              self.framer.clear();
+              let _ = reply.send(Ok(()));
+            }
+            Ok(Command::SetDataCallback(callback, reply)) => {
+              self.on_data_received = Some(callback);
+              let _ = reply.send(Ok(()));
+            }
+            Ok(Command::CancelRead(id)) => {
+              if self.pending_read.as_ref().is_some_and(|r| r.id == id) {
+                self.fail_read("read cancelled");
+              }
+            }
+            Ok(cmd) => execute(&mut self.port, cmd),
+            Err(_) => {}
+          }
+        }
+        default(idle_wait) => {
+          self.expire_read();
+          self.expire_held();
+          self.expire_gap(last_data);
+
+          if let Some(interval) = self.liveness_poll {
+            if last_poll.elapsed() >= interval {
+              last_poll = Instant::now();
+              // any cheap query fails once the device is really gone
+              if let Err(e) = self.port.bytes_to_read() {
+                if !self.lost(&std::io::Error::from(e), &kill_rx, &cmd_rx) {
+                  break;
+                }
+                continue;
+              }
+            }
+          }
+
+          if last_overrun_check.elapsed() >= OVERRUN_POLL_INTERVAL {
+            last_overrun_check = Instant::now();
+            self.check_overruns();
+          }
+
+          // Leave incoming bytes with the OS while paused, unless someone explicitly asked for them
+          if self.state.paused.load(Ordering::Relaxed) && self.pending_read.is_none() {
+            thread::sleep(PAUSED_POLL_INTERVAL);
+            continue;
+          }
+
+          // a one-shot read takes the next bytes, but no more than it asked for
+          let len = self
+            .pending_read
+            .as_ref()
+            .and_then(ReadRequest::wanted)
+            .unwrap_or(self.read_buffer_size);
+          if buf.len() < len {
+            buf.resize(len, 0);
+          }
+
+          match self.port.read(&mut buf[..len]) {
+            Ok(n) if n > 0 => {
+              last_data = Instant::now();
+              self.read_at = last_data;
+              idle_wait = Duration::ZERO;
+              self.state.stats.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+              if let Some(trace) = &self.trace {
+                if let Err(e) = trace.record(Direction::Rx, &buf[..n]) {
+                  self.errors.report(PortError::from_io("failed to write the trace file", &e));
+                }
+              }
+              if self.discard_until.is_some_and(|until| last_data < until) {
+                continue;
+              }
+              let echoed = self.echo.as_ref().map_or(0, |echo| echo.strip(&buf[..n]));
+              if echoed == n {
+                continue;
+              }
+              buf.copy_within(echoed..n, 0);
+              let n = n - echoed;
+              if self.parity_marks.is_some() {
+                let data = self.strip_parity_marks(&buf[..n]);
+                let taken = self.feed_read(&data);
+                if taken < data.len() {
+                  self.receive(&data[taken..]);
+                }
+                continue;
+              }
+
+              let taken = self.feed_read(&buf[..n]);
+              if taken < n {
+                self.receive(&buf[taken..n]);
+              }
+            }
+            // zero bytes, same as no data
+            Ok(_) => idle_wait = self.back_off(idle_wait),
+            // normal: no data this iteration
+            Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
+              idle_wait = self.back_off(idle_wait);
+            }
+            // the device went away -> stop accepting work and wait for it to come back
+            Err(e) if is_disconnect(&e) => {
+              if !self.lost(&e, &kill_rx, &cmd_rx) {
+                break;
+              }
+            }
+            // unrecoverable error or port closed -> exit
+            Err(e) => {
+              self.state.stats.read_errors.fetch_add(1, Ordering::Relaxed);
+              self.errors.report(PortError::from_io("read thread died", &e));
+              break;
+            }
+          }
+        }
+      }
+    }
+  }
+
+  // Report bytes the driver had to throw away since the last check
+  fn check_overruns(&mut self) {
+    let (Some(seen), Some(count)) = (self.overruns, overrun_count(&self.port)) else {
+      return;
+    };
+
+    if count > seen {
+      self.errors.report(PortError::new(
+        PortErrorKind::Overrun,
+        format!(
+          "the driver reported {} receive overruns, incoming data was lost",
+          count - seen
+        ),
+      ));
+    }
+    self.overruns = Some(count);
+  }
+
+  fn set_framer(&mut self, framer: Framer, reply: Reply<()>) {
+    let dropped = self.framer.buffered();
+    if dropped > 0 {
+      self.errors.report(PortError::new(
+        PortErrorKind::InvalidFrame,
+        format!("dropped a partial frame of {dropped} bytes when switching framing"),
+      ));
+    }
+
+    self.framer = framer;
+    let _ = reply.send(Ok(()));
+  }
+
+  fn set_timeout(&mut self, timeout: Duration, reply: Reply<()>) {
+    // a reopened device should carry on with the new timeout
+    if let Some(reconnect) = &mut self.reconnect {
+      reconnect.line.timeout = timeout;
+    }
+
+    execute(&mut self.port, Command::SetTimeout(timeout, reply));
+  }
+
+  fn set_flow_control(&mut self, flow_control: serialport::FlowControl, reply: Reply<()>) {
+    // a reopened device should carry on with the new flow control too
+    if let Some(reconnect) = &mut self.reconnect {
+      reconnect.line.flow_control = flow_control;
+    }
+
+    execute(&mut self.port, Command::SetFlowControl(flow_control, reply));
+  }
+
+  // Take the error markers out of freshly read bytes and report what they flagged
+  fn strip_parity_marks(&mut self, data: &[u8]) -> Vec<u8> {
+    let Some(parity_marks) = &mut self.parity_marks else {
+      return data.to_vec();
+    };
+
+    let (data, marks) = parity_marks.strip(data);
+    for mark in marks {
+      let message = match mark {
+        Mark::Error(offset) => format!("byte {offset} arrived with a parity or framing error"),
+        Mark::Break(offset) => format!("break received before byte {offset}"),
+      };
+      self
+        .errors
+        .report(PortError::new(PortErrorKind::ParityError, message));
+    }
+
+    data
+  }
+
+  // Wait a little longer before the next poll of a port that keeps coming up empty
+  fn back_off(&self, idle_wait: Duration) -> Duration {
+    match self.max_idle_wait {
+      // a one-shot read is waiting, keep polling at full speed
+      _ if self.pending_read.is_some() => Duration::ZERO,
+      Some(max) => (idle_wait * 2).max(IDLE_BACKOFF_START).min(max),
+      None => Duration::ZERO,
+    }
+  }
+
+  fn start_read(&mut self, mut request: ReadRequest) {
+    if self.pending_read.is_some() {
+      let _ = request.reply.send(Err(napi::Error::from_reason(
+        "another read is already pending",
+      )));
+      return;
+    }
+
+    if let Some(armed) = request.armed.take() {
+      let _ = armed.send(());
+    }
+    self.pending_read = Some(request);
+  }
+
+  // Give freshly read bytes to the pending one-shot read, returns how many it took
+  fn feed_read(&mut self, data: &[u8]) -> usize {
+    let Some(request) = &mut self.pending_read else {
+      return 0;
+    };
+
+    let (taken, complete) = request.feed(data);
+    if complete {
+      self.finish_read();
+    }
+
+    taken
+  }
+
+  fn finish_read(&mut self) {
+    if let Some(request) = self.pending_read.take() {
+      let _ = request.reply.send(Ok(Buffer::from(request.collected)));
+    }
+  }
+
+  fn expire_read(&mut self) {
+    let Some(request) = &self.pending_read else {
+      return;
+    };
+
+    if Instant::now() < request.deadline {
+      // a reply without a terminator is over once the line goes quiet after it started
+      if let ReadUntil::Reply {
+        idle: Some(idle), ..
+      } = request.until
+      {
+        if request.last_data.is_some_and(|last| last.elapsed() >= idle) {
+          self.finish_read();
+        }
+      }
+      return;
+    }
+
+    match request.until {
+      ReadUntil::AnyData(_) => self.fail_read("read timed out"),
+      ReadUntil::Exact(len) => {
+        let collected = &request.collected;
+        let hex: Vec<_> = collected.iter().map(|byte| format!("{byte:02x}")).collect();
+        let reason = format!(
+          "read timed out after {} of {len} bytes: [{}]",
+          collected.len(),
+          hex.join(" ")
+        );
+        self.fail_read(&reason);
+      }
+      // replies resolve with whatever arrived in time
+      ReadUntil::Reply { .. } => self.finish_read(),
+    }
+  }
+
+  fn fail_read(&mut self, reason: &str) {
+    if let Some(request) = self.pending_read.take() {
+      let _ = request.reply.send(Err(napi::Error::from_reason(reason)));
+    }
+  }
+
+  // Hold back reads smaller than `min_read_bytes`, then deliver them
+  fn receive(&mut self, data: &[u8]) {
+    let Some(coalesce) = &mut self.coalesce else {
+      return self.deliver(data);
+    };
+
+    if coalesce.held.is_empty() {
+      coalesce.since = Instant::now();
+    }
+    coalesce.held.extend_from_slice(data);
+
+    if coalesce.held.len() >= coalesce.min_bytes {
+      self.flush_held();
+    }
+  }
+
+  fn expire_held(&mut self) {
+    if self
+      .coalesce
+      .as_ref()
+      .is_some_and(|c| !c.held.is_empty() && c.since.elapsed() >= c.timeout)
+    {
+      self.flush_held();
+    }
+  }
+
+  fn flush_held(&mut self) {
+    let Some(coalesce) = &mut self.coalesce else {
+      return;
+    };
+
+    let held = std::mem::take(&mut coalesce.held);
+    if !held.is_empty() {
+      self.deliver(&held);
+    }
+  }
+
+  fn deliver(&mut self, data: &[u8]) {
+    for event in self.framer.push(data) {
+      self.dispatch(event);
+    }
+  }
+
+  // End the frame in progress if the line has been quiet for long enough
+  fn expire_gap(&mut self, last_data: Instant) {
+    if self
+      .framer
+      .gap()
+      .is_some_and(|gap| last_data.elapsed() >= gap)
+    {
+      if let Some(event) = self.framer.flush() {
+        self.dispatch(event);
+      }
+    }
+  }
+
+  fn dispatch(&mut self, event: FrameEvent) {
+    match event {
+      FrameEvent::Frame(frame) => {
+        let data = match &self.text {
+          None => Either::A(Buffer::from(frame)),
+          Some(text) => {
+            let len = frame.len();
+            match text.decode(frame) {
+              Some(text) => Either::B(text),
+              None => {
+                self.errors.report(PortError::new(
+                  PortErrorKind::InvalidFrame,
+                  format!("dropped a {len} byte frame that isn't valid text"),
+                ));
+                return;
+              }
+            }
+          }
+        };
+        let data = match self.opened_at {
+          Some(opened_at) => Either3::C(TimestampedData {
+            data,
+            timestamp_ns: self.read_at.saturating_duration_since(opened_at).as_nanos() as i64,
+          }),
+          None => match data {
+            Either::A(buf) => Either3::A(buf),
+            Either::B(text) => Either3::B(text),
+          },
+        };
+
+        // a full queue means nobody is awaiting `next_frame`, the frame is dropped rather than held up
+        let state = &self.state;
+        self
+          .frame_txs
+          .retain(|frame_tx| match frame_tx.send(copy_data(&data)) {
+            Pushed::Queued => true,
+            Pushed::Dropped => {
+              state
+                .stats
+                .dropped_callbacks
+                .fetch_add(1, Ordering::Relaxed);
+              true
+            }
+            Pushed::Closed => false,
+          });
+
+        // every listener gets its own copy, JS may hold on to or modify the buffer it was given
+        for (_, listener) in self.state.listeners.snapshot().iter() {
+          self.call_data(listener, copy_data(&data));
+        }
+        if let Some(on_data_received) = &self.on_data_received {
+          self.call_data(on_data_received, data);
+        }
+      }
+      FrameEvent::Overflow { len, delivered } => {
+        let message = if delivered {
+          format!("{len} bytes without a frame boundary, delivering them as truncated frames")
+        } else {
+          format!("dropped {len} bytes without a frame boundary")
+        };
+        self
+          .errors
+          .report(PortError::new(PortErrorKind::FrameOverflow, message));
+      }
+      FrameEvent::Invalid(len) => {
+        self.errors.report(PortError::new(
+          PortErrorKind::InvalidFrame,
+          format!("dropped a {len} byte frame that failed to decode or its checksum"),
+        ));
+      }
+    }
+  }
+
+  // Hand data to one callback, in non-blocking mode dropping it if JS is too far behind
+  fn call_data(&self, callback: &OnDataReceivedCallback, data: Data) {
+    let thrown = self.thrown.clone();
+    if matches!(self.call_mode, ThreadsafeFunctionCallMode::Blocking) {
+      let _ = callback.call_with_return_value(
+        data,
+        ThreadsafeFunctionCallMode::Blocking,
+        move |ret, _| thrown.handle(ret),
+      );
+      return;
+    }
+
+    let pending = &self.state.pending_callbacks;
+    let dropped = || {
+      pending.fetch_sub(1, Ordering::AcqRel);
+      self
+        .state
+        .stats
+        .dropped_callbacks
+        .fetch_add(1, Ordering::Relaxed);
+    };
+    if pending.fetch_add(1, Ordering::AcqRel) >= MAX_PENDING_CALLBACKS {
+      dropped();
+      return;
+    }
+
+    let state = self.state.clone();
+    let status = callback.call_with_return_value(
+      data,
+      ThreadsafeFunctionCallMode::NonBlocking,
+      move |ret, _| {
+        state.pending_callbacks.fetch_sub(1, Ordering::AcqRel);
+        thrown.handle(ret)
+      },
+    );
+    if status != napi::Status::Ok {
+      dropped();
+    }
+  }
+
+  // The device went away, returns false if the thread should exit instead of carrying on
+  fn lost(
+    &mut self,
+    e: &std::io::Error,
+    kill_rx: &Receiver<()>,
+    cmd_rx: &Receiver<Command>,
+  ) -> bool {
+    // closing, e.g. a loopback loses its other end along with the write thread
+    if !matches!(kill_rx.try_recv(), Err(TryRecvError::Empty)) {
+      return false;
+    }
+
+    self.state.stats.read_errors.fetch_add(1, Ordering::Relaxed);
+    self.disconnected(e);
+    self.reconnect(kill_rx, cmd_rx)
+  }
+
+  fn disconnected(&mut self, e: &std::io::Error) {
+    // whatever made it across before the device went away is still valid
+    self.flush_held();
+    self.state.disconnected.store(true, Ordering::Relaxed);
+    self.fail_read("device disconnected");
+
+    self.errors.disconnected(PortError {
+      // the liveness poll can notice a disconnect through an otherwise generic error
+      kind: PortErrorKind::Disconnected,
+      ..PortError::from_io("device disconnected", e)
+    });
+  }
+
+  // Keep trying to reopen the device, returns false if the thread should exit instead
+  fn reconnect(&mut self, kill_rx: &Receiver<()>, cmd_rx: &Receiver<Command>) -> bool {
+    let Some(interval) = self.reconnect.as_ref().map(|r| r.interval) else {
+      return false;
+    };
+
+    loop {
+      crossbeam::select! {
+        // Shutdown requested
+        recv(kill_rx) -> _ => return false,
+        // The old handle is dead, so commands for the port just report the OS error.
+        // Those that only change this thread's state still apply and carry over to the new handle.
+        recv(cmd_rx) -> cmd => {
+          match cmd {
+            Ok(Command::SubscribeFrames(frame_tx)) => self.frame_txs.push(frame_tx),
+            Ok(Command::SetFramer(framer, reply)) => self.set_framer(*framer, reply),
+            Ok(Command::ResetFrameBuffer(reply)) => {
+              self.framer.clear();
+              let _ = reply.send(Ok(()));
+            }
+            Ok(Command::SetDataCallback(callback, reply)) => {
+              self.on_data_received = Some(callback);
+              let _ = reply.send(Ok(()));
+            }
+            Ok(cmd) => execute(&mut self.port, cmd),
+            Err(_) => {}
+          }
+        }
+        default(interval) => {
+          let Some(reconnect) = &mut self.reconnect else {
+            return false;
+          };
+          let Some((read_port, write_port)) = reconnect.try_reopen() else {
+            continue;
+          };
+
+          // the write thread is gone, nothing left to reconnect
+          if let Some(port_tx) = &reconnect.port_tx {
+            if port_tx.send(write_port).is_err() {
+              return false;
+            }
+          }
+
+          self.overruns = overrun_count(&read_port);
+          self.port = read_port;
+          // whatever was buffered belongs to the old connection
+          self.framer.clear();
+          // the device probably just booted again
+          self.discard_until = self.discard_after_open.map(|discard| Instant::now() + discard);
+          if let Some(parity_marks) = &mut self.parity_marks {
+            *parity_marks = ParityMarks::default();
+            if let Err(e) = enable_parity_marking(&self.port) {
+              self.errors.report(PortError::from_io("failed to re-enable parity error reporting", &e));
+            }
+          }
+          self.state.disconnected.store(false, Ordering::Relaxed);
+
+          self.errors.reconnected(&reconnect.path);
+
+          return true;
+        }
+      }
+    }
+  }
+}
+
+// A copy of a frame for one more consumer
+fn copy_data(data: &Data) -> Data {
+  match data {
+    Either3::A(buf) => Either3::A(Buffer::from(buf.to_vec())),
+    Either3::B(text) => Either3::B(text.clone()),
+    Either3::C(timestamped) => Either3::C(TimestampedData {
+      data: match &timestamped.data {
+        Either::A(buf) => Either::A(Buffer::from(buf.to_vec())),
+        Either::B(text) => Either::B(text.clone()),
+      },
+      timestamp_ns: timestamped.timestamp_ns,
+    }),
+  }
+}
+
+impl ReadRequest {
+  pub fn next_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+  }
+
+  // How many bytes the next read may take without overshooting this request
+  fn wanted(&self) -> Option<usize> {
+    match &self.until {
+      ReadUntil::AnyData(max_len) => Some(*max_len),
+      ReadUntil::Exact(len) => Some(len - self.collected.len()),
+      ReadUntil::Reply {
+        length: Some(length),
+        ..
+      } => Some(length - self.collected.len()),
+      ReadUntil::Reply { .. } => None,
+    }
+  }
+
+  // Take what this request needs from `data`, returns how many bytes it took and whether it is complete
+  fn feed(&mut self, data: &[u8]) -> (usize, bool) {
+    match &self.until {
+      ReadUntil::AnyData(_) => {
+        self.collected.extend_from_slice(data);
+        (data.len(), true)
+      }
+      ReadUntil::Exact(len) => {
+        let taken = data.len().min(len - self.collected.len());
+        self.collected.extend_from_slice(&data[..taken]);
+        (taken, self.collected.len() == *len)
+      }
+      ReadUntil::Reply {
+        delimiter, length, ..
+      } => {
+        self.last_data = Some(Instant::now());
+        let before = self.collected.len();
+        let mut taken = match length {
+          Some(length) => data.len().min(length - before),
+          None => data.len(),
+        };
+        self.collected.extend_from_slice(&data[..taken]);
+
+        let mut complete = length.is_some_and(|length| self.collected.len() >= length);
+
+        if let Some(delimiter) = delimiter {
+          // only rescan the tail that could hold a delimiter split across reads
+          let start = before.saturating_sub(delimiter.len() - 1);
+          if let Some(pos) = self.collected[start..]
+            .windows(delimiter.len())
+            .position(|window| window == delimiter.as_slice())
+          {
+            let end = start + pos + delimiter.len();
+            // anything past the delimiter isn't part of the reply
+            taken -= self.collected.len() - end;
+            self.collected.truncate(end);
+            complete = true;
+          }
+        }
+
+        (taken, complete)
+      }
+    }
+  }
+}
+
+impl Reconnect {
+  fn try_reopen(&mut self) -> Option<(NativePort, NativePort)> {
+    // `on_reconnect` is handed the path, which tells whoever cares that the device moved
+    self.path = self.find_path()?;
+
+    // there's only a write thread to hand a handle to if the port is open for writing
+    let (read_port, mut write_port) = open_native_pair(
+      &self.line,
+      &self.path,
+      self.exclusive,
+      self.initial_rts,
+      true,
+      self.port_tx.is_some(),
+    )
+    .ok()?;
+
+    if self.one_point_five_stop_bits && enable_one_point_five_stop_bits(&read_port).is_err() {
+      return None;
+    }
+    if self.stick_parity && enable_stick_parity(&read_port).is_err() {
+      return None;
+    }
+    if let Some(bytes) = self.os_read_buffer_bytes {
+      let _ = set_input_buffer_size(&read_port, bytes);
+    }
+    if self.low_latency {
+      let _ = enable_low_latency(&read_port);
+    }
+    if let Some(rs485) = &self.rs485 {
+      if self.kernel_rs485 {
+        enable_kernel_rs485(&read_port, rs485).ok()?;
+      } else {
+        // the write thread keeps toggling RTS by hand, the transceiver only needs parking again
+        write_port
+          .write_request_to_send(bus_is_idle_high(rs485))
+          .ok()?;
+      }
+    }
+
+    Some((read_port, write_port))
+  }
+
+  // Where the device currently lives, if it is back at all
+  fn find_path(&self) -> Option<String> {
+    let ports = serialport::available_ports().ok()?;
+    if ports.iter().any(|p| p.port_name == self.path) {
+      return Some(self.path.clone());
+    }
+
+    let usb = self.usb.as_ref()?;
+    ports
+      .into_iter()
+      .find(|p| match &p.port_type {
+        SerialPortType::UsbPort(info) => {
+          info.vid == usb.vid
+            && info.pid == usb.pid
+            && info.serial_number.as_deref() == Some(usb.serial.as_str())
+        }
+        _ => false,
+      })
+      .map(|p| p.port_name)
+  }
+}
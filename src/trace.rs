@@ -0,0 +1,102 @@
+// Tees the raw traffic of a port into a log file, rotated once it grows too large
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+#[derive(Clone, Copy)]
+pub(crate) enum Direction {
+  Rx,
+  Tx,
+}
+
+// Shared by both worker threads, the lock keeps their records from interleaving
+pub(crate) struct Trace {
+  // None once writing failed, tracing stops instead of failing every record
+  file: Mutex<Option<TraceFile>>,
+}
+
+struct TraceFile {
+  path: PathBuf,
+  file: File,
+  size: u64,
+  max_size: u64,
+}
+
+impl Trace {
+  pub fn open(path: &str, max_size: u64) -> std::io::Result<Self> {
+    let path = PathBuf::from(path);
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let size = file.metadata()?.len();
+
+    Ok(Trace {
+      file: Mutex::new(Some(TraceFile {
+        path,
+        file,
+        size,
+        max_size,
+      })),
+    })
+  }
+
+  // Append one record, an error is only returned for the first failure
+  pub fn record(&self, direction: Direction, data: &[u8]) -> std::io::Result<()> {
+    let mut file = self.file.lock().unwrap();
+    let Some(trace) = file.as_mut() else {
+      return Ok(());
+    };
+
+    let res = trace.append(direction, data);
+    if res.is_err() {
+      *file = None;
+    }
+    res
+  }
+}
+
+impl TraceFile {
+  // One line per record: milliseconds since the epoch, RX or TX, then the bytes in hex
+  fn append(&mut self, direction: Direction, data: &[u8]) -> std::io::Result<()> {
+    let millis = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_millis();
+    let direction = match direction {
+      Direction::Rx => "RX",
+      Direction::Tx => "TX",
+    };
+
+    let mut line = format!("{millis} {direction} ");
+    for b in data {
+      line.push(HEX_DIGITS[(b >> 4) as usize] as char);
+      line.push(HEX_DIGITS[(b & 0x0F) as usize] as char);
+    }
+    line.push('\n');
+
+    if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+      self.rotate()?;
+    }
+
+    self.file.write_all(line.as_bytes())?;
+    self.size += line.len() as u64;
+    Ok(())
+  }
+
+  // Keep a single previous file next to the current one, replacing any older one
+  fn rotate(&mut self) -> std::io::Result<()> {
+    let mut previous = self.path.clone().into_os_string();
+    previous.push(".1");
+    std::fs::rename(&self.path, previous)?;
+
+    self.file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)?;
+    self.size = 0;
+    Ok(())
+  }
+}
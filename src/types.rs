@@ -1,6 +1,8 @@
-use napi::bindgen_prelude::ToNapiValue;
+use crossbeam::channel::{Receiver, Sender};
+use napi::bindgen_prelude::{Buffer, Either, ToNapiValue};
 use napi_derive::napi;
 
+#[derive(PartialEq)]
 #[napi(string_enum)]
 pub enum DataBits {
   Five,
@@ -9,19 +11,28 @@ pub enum DataBits {
   Eight,
 }
 
+#[derive(PartialEq)]
 #[napi(string_enum)]
 pub enum Parity {
   None,
   Odd,
   Even,
+  /// the parity bit is always 1, Linux and Windows only
+  Mark,
+  /// the parity bit is always 0, Linux and Windows only
+  Space,
 }
 
+#[derive(PartialEq)]
 #[napi(string_enum)]
 pub enum StopBits {
   One,
+  /// only with five data bits, opening the port with any other number fails as unsupported
+  OnePointFive,
   Two,
 }
 
+#[derive(PartialEq)]
 #[napi(string_enum)]
 pub enum FlowControl {
   None,
@@ -29,6 +40,197 @@ pub enum FlowControl {
   Hardware,
 }
 
+/// The kinds of port `AvailablePort.type` reports
+#[napi(string_enum)]
+pub enum PortKind {
+  Usb,
+  Bluetooth,
+  Pci,
+  Unknown,
+}
+
+/// How incoming data is split into frames, packet encodings also apply to writes
+#[napi(discriminant = "type")]
+pub enum Framing {
+  /// deliver reads as they arrive
+  Raw,
+  /// end a frame at `bytes`, keeping them at its end when `include` is set
+  Delimiter {
+    bytes: Buffer,
+    include: Option<bool>,
+  },
+  /// end a frame at whichever of `bytes` comes first, e.g. for mixed line endings, keeping it when `include` is set
+  Delimiters {
+    bytes: Vec<Buffer>,
+    include: Option<bool>,
+  },
+  /// deliver exactly `len` bytes at a time
+  FixedLength { len: u32 },
+  /// end a frame once no byte arrived for `ms`
+  InterByteTimeout { ms: u32 },
+  /// Consistent Overhead Byte Stuffing, packets end in a 0x00 byte
+  Cobs,
+  /// Modbus RTU, frames end with a pause of `gap_ms` (defaults to 3.5 characters) and carry a trailing CRC-16
+  ModbusRtu { gap_ms: Option<u32> },
+}
+
+/// What happens to a partial frame that grows past `max_frame_size`
+#[derive(PartialEq)]
+#[napi(string_enum)]
+pub enum FrameOverflow {
+  /// throw the buffered bytes away
+  Drop,
+  /// hand them to `on_data_received` in frames of `max_frame_size` bytes
+  Deliver,
+}
+
+/// How the read thread queues calls to `on_data_received` and the data listeners
+#[derive(PartialEq)]
+#[napi(string_enum)]
+pub enum CallbackMode {
+  /// queue every frame, however far behind JS falls
+  Blocking,
+  /// drop frames while JS is too far behind, counting them in `dropped_callbacks`
+  NonBlocking,
+}
+
+/// What happens when `on_data_received` or a data listener throws
+#[derive(PartialEq)]
+#[napi(string_enum)]
+pub enum CallbackErrors {
+  /// leave it uncaught, which ends the process unless an `uncaughtException` handler is installed
+  Throw,
+  /// swallow it and carry on with the next frame
+  Ignore,
+  /// hand it to `on_error` as a `CallbackThrew` error and carry on
+  Report,
+}
+
+/// Text encodings `on_data_received` can decode frames with
+#[derive(PartialEq)]
+#[napi(string_enum)]
+pub enum TextEncoding {
+  Utf8,
+  /// 7-bit ASCII, bytes above 0x7F are invalid
+  Ascii,
+  /// ISO-8859-1, every byte maps to the code point of the same value
+  Latin1,
+}
+
+/// Which directions a port is opened for
+#[derive(PartialEq)]
+#[napi(string_enum)]
+pub enum AccessMode {
+  /// only read, `write` and friends fail
+  Read,
+  /// only write, nothing is read and `on_data_received` is never called
+  Write,
+  ReadWrite,
+}
+
+// Packet delimiters are compared by their bytes
+impl PartialEq for Framing {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (
+        Framing::Delimiter { bytes, include },
+        Framing::Delimiter {
+          bytes: other_bytes,
+          include: other_include,
+        },
+      ) => bytes.as_ref() == other_bytes.as_ref() && include == other_include,
+      (
+        Framing::Delimiters { bytes, include },
+        Framing::Delimiters {
+          bytes: other_bytes,
+          include: other_include,
+        },
+      ) => {
+        bytes.len() == other_bytes.len()
+          && bytes
+            .iter()
+            .zip(other_bytes)
+            .all(|(a, b)| a.as_ref() == b.as_ref())
+          && include == other_include
+      }
+      (Framing::FixedLength { len }, Framing::FixedLength { len: other_len }) => len == other_len,
+      (Framing::InterByteTimeout { ms }, Framing::InterByteTimeout { ms: other_ms }) => {
+        ms == other_ms
+      }
+      (
+        Framing::ModbusRtu { gap_ms },
+        Framing::ModbusRtu {
+          gap_ms: other_gap_ms,
+        },
+      ) => gap_ms == other_gap_ms,
+      _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+    }
+  }
+}
+
+impl From<serialport::DataBits> for DataBits {
+  fn from(db: serialport::DataBits) -> Self {
+    match db {
+      serialport::DataBits::Five => DataBits::Five,
+      serialport::DataBits::Six => DataBits::Six,
+      serialport::DataBits::Seven => DataBits::Seven,
+      serialport::DataBits::Eight => DataBits::Eight,
+    }
+  }
+}
+
+impl From<serialport::Parity> for Parity {
+  fn from(p: serialport::Parity) -> Self {
+    match p {
+      serialport::Parity::None => Parity::None,
+      serialport::Parity::Odd => Parity::Odd,
+      serialport::Parity::Even => Parity::Even,
+    }
+  }
+}
+
+impl From<serialport::StopBits> for StopBits {
+  fn from(sb: serialport::StopBits) -> Self {
+    match sb {
+      serialport::StopBits::One => StopBits::One,
+      serialport::StopBits::Two => StopBits::Two,
+    }
+  }
+}
+
+impl From<serialport::FlowControl> for FlowControl {
+  fn from(fc: serialport::FlowControl) -> Self {
+    match fc {
+      serialport::FlowControl::None => FlowControl::None,
+      serialport::FlowControl::Software => FlowControl::Software,
+      serialport::FlowControl::Hardware => FlowControl::Hardware,
+    }
+  }
+}
+
+impl From<FlowControl> for serialport::FlowControl {
+  fn from(fc: FlowControl) -> Self {
+    match fc {
+      FlowControl::None => serialport::FlowControl::None,
+      FlowControl::Software => serialport::FlowControl::Software,
+      FlowControl::Hardware => serialport::FlowControl::Hardware,
+    }
+  }
+}
+
+/// Half-duplex RS-485 with RTS enabling the transmitter
+#[derive(Clone, PartialEq)]
+#[napi(object)]
+pub struct Rs485Settings {
+  /// RTS level while transmitting (defaults to true)
+  pub rts_on_send: Option<bool>,
+  /// RTS level while idle, for transceivers that want the same level both ways (defaults to the opposite of `rts_on_send`)
+  pub bus_is_idle_high: Option<bool>,
+  /// how long to hold RTS after the last byte left the port in microseconds (defaults to 0)
+  pub rts_after_send_delay_us: Option<u32>,
+}
+
+#[derive(Default)]
 #[napi(object)]
 pub struct PortSettings {
   pub baud_rate: Option<u32>,
@@ -38,6 +240,243 @@ pub struct PortSettings {
   pub parity: Option<Parity>,
   pub stop_bits: Option<StopBits>,
   pub flow_control: Option<FlowControl>,
+  /// size of each read from the port in bytes, between 1 and 1 MiB (defaults to 1024)
+  pub read_buffer_size: Option<u32>,
+  /// ask the driver for an input buffer of this many bytes, to ride out stalls of the read thread at high baud rates.
+  /// Best effort: only Windows lets us ask, and drivers may round or ignore it; elsewhere it is ignored
+  pub os_read_buffer_bytes: Option<u32>,
+  /// have the driver hand over received bytes right away, e.g. instead of after the 16 ms latency timer of FTDI adapters
+  /// (defaults to false, Linux only and ignored elsewhere)
+  pub low_latency: Option<bool>,
+  /// drop everything read for this many ms after opening or reconnecting, e.g. a boot banner
+  pub discard_ms_after_open: Option<u32>,
+  /// back off polling a quiet port, waiting up to this many ms between polls once nothing arrives (defaults to no backoff)
+  pub max_idle_poll_ms: Option<u32>,
+  /// label copied into every `PortError` from this port, to tell ports apart in a shared handler
+  pub tag: Option<String>,
+  /// stop other processes from opening the port while it is open (defaults to false, ignored on Windows)
+  pub exclusive: Option<bool>,
+  /// level to drive DTR to while opening, e.g. false to keep a board from resetting (defaults to the OS behaviour)
+  pub initial_dtr: Option<bool>,
+  /// level to drive RTS to right after opening, before any data flows (defaults to the OS behaviour)
+  pub initial_rts: Option<bool>,
+  /// keep reopening the port after the device goes away instead of giving up (defaults to false)
+  pub auto_reconnect: Option<bool>,
+  /// how long to wait between reconnect attempts in ms (defaults to 1000)
+  pub reconnect_interval_ms: Option<u32>,
+  /// treat the device as disconnected if a status query fails, checked this often in ms
+  pub liveness_poll_ms: Option<u32>,
+  /// drive RTS around every write for an RS-485 transceiver
+  pub rs485: Option<Rs485Settings>,
+  /// drop the echo of our own writes from incoming data, for half-duplex lines that hand it back (defaults to false)
+  pub suppress_local_echo: Option<bool>,
+  /// how long an echo may take to come back after the write or the previous echoed byte, in ms (defaults to 100)
+  pub local_echo_window_ms: Option<u32>,
+  /// give up on a write that takes longer than this many ms and move on to the next one
+  pub write_timeout_ms: Option<u32>,
+  /// hand writes to the OS at most this many bytes at a time, letting priority writes go out in between (defaults to whole writes)
+  pub write_chunk_size: Option<u32>,
+  /// gather writes queued within this many ms of each other into one write and flush (defaults to writing each on its own)
+  pub write_coalesce_ms: Option<u32>,
+  /// most writes to gather into one, sending the batch early once reached (defaults to unlimited)
+  pub write_coalesce_count: Option<u32>,
+  /// maximum number of queued writes before `write` starts failing (defaults to unbounded)
+  pub write_queue_capacity: Option<u32>,
+  /// hold back reads until at least this many bytes arrived before calling `on_data_received`
+  pub min_read_bytes: Option<u32>,
+  /// deliver held bytes anyway once the oldest has waited this long in ms (defaults to 20)
+  pub min_read_timeout_ms: Option<u32>,
+  /// how incoming data is split into frames, and for packet encodings how writes are encoded (defaults to raw)
+  pub framing: Option<Framing>,
+  /// deliver data one frame at a time, split on this delimiter
+  ///
+  /// @deprecated use `framing: { type: 'Delimiter', bytes }`
+  pub frame_delimiter: Option<Buffer>,
+  /// deliver data one frame at a time, split on whichever of these delimiters comes first, e.g. for mixed line endings
+  ///
+  /// @deprecated use `framing: { type: 'Delimiters', bytes }`
+  pub frame_delimiters: Option<Vec<Buffer>>,
+  /// keep the delimiter at the end of each frame (defaults to false)
+  ///
+  /// @deprecated use the `include` of a `Delimiter` or `Delimiters` framing
+  pub include_delimiter: Option<bool>,
+  /// deliver data in frames of exactly this many bytes, cannot be combined with `frame_delimiter`
+  ///
+  /// @deprecated use `framing: { type: 'FixedLength', len }`
+  pub frame_length: Option<u32>,
+  /// end a frame once no byte arrived for this many ms, e.g. the 3.5 character silence of Modbus RTU
+  ///
+  /// @deprecated use `framing: { type: 'InterByteTimeout', ms }`, or the `gapMs` of a `ModbusRtu` framing
+  pub inter_byte_timeout_ms: Option<u32>,
+  /// largest partial frame to buffer before dropping it and reporting an error
+  pub max_frame_size: Option<u32>,
+  /// whether a partial frame past `max_frame_size` is dropped or delivered truncated (defaults to dropping it)
+  pub frame_overflow: Option<FrameOverflow>,
+  /// deliver every frame as a string decoded with this encoding, split into lines unless other framing is set
+  pub encoding: Option<TextEncoding>,
+  /// drop frames that aren't valid text and report an error instead of replacing the bad bytes (defaults to false)
+  pub strict_decoding: Option<bool>,
+  /// whether data callbacks are dropped rather than queued while JS is behind (defaults to blocking)
+  pub callback_mode: Option<CallbackMode>,
+  /// what happens when a data callback throws (defaults to leaving it uncaught)
+  pub callback_errors: Option<CallbackErrors>,
+  /// report bytes received with a parity or framing error through `on_error` (defaults to false, Unix only)
+  pub report_parity_errors: Option<bool>,
+  /// append every byte read and written to this file, one timestamped line per read or write
+  pub trace_file: Option<String>,
+  /// start a new trace file once it would grow past this many bytes, keeping one previous file (defaults to 10 MiB)
+  pub trace_file_max_bytes: Option<u32>,
+  /// share the device with other handles opened on the same path with this set, within this process (defaults to false)
+  pub shared: Option<bool>,
+  /// open the device for reading or writing only, skipping the worker thread for the other direction (defaults to both)
+  pub access_mode: Option<AccessMode>,
+  /// start no worker threads, data is only read and written by calling `read` and `write_sync` (defaults to false)
+  pub manual_mode: Option<bool>,
+  /// hand data callbacks a `TimestampedData` carrying when each frame was read, instead of the bare data (defaults to false)
+  pub timestamp_reads: Option<bool>,
+}
+
+#[derive(Clone, Copy)]
+#[napi(string_enum)]
+pub enum PortErrorKind {
+  /// the device went away, e.g. a USB adapter was unplugged
+  Disconnected,
+  PermissionDenied,
+  NotFound,
+  TimedOut,
+  /// a partial frame grew past `max_frame_size` and was dropped, or delivered truncated
+  FrameOverflow,
+  /// the OS is running the port at a different baud rate than requested
+  BaudRateMismatch,
+  /// received bytes that couldn't be decoded as a frame and were dropped
+  InvalidFrame,
+  /// a byte arrived with a parity or framing error, or a break was received
+  ParityError,
+  /// received bytes were lost because the driver's buffer was full, e.g. JS fell too far behind (Linux only)
+  Overrun,
+  /// the port couldn't be opened because another application has it open
+  PortBusy,
+  /// a data callback threw, reported when `callback_errors` is `Report`
+  CallbackThrew,
+  /// any other I/O failure
+  Io,
+  Other,
+}
+
+/// How far a write spanning several chunks has got, handed to the write progress callback
+#[napi(object)]
+pub struct WriteProgress {
+  /// bytes handed to the OS so far, after any packet encoding
+  pub written: i64,
+  pub total: i64,
+}
+
+/// A frame together with when it was read, delivered when `timestamp_reads` is set
+#[napi(object)]
+pub struct TimestampedData {
+  /// the frame, or its text when `encoding` is set
+  pub data: Either<Buffer, String>,
+  /// nanoseconds from opening the port until the read that completed the frame returned
+  pub timestamp_ns: i64,
+}
+
+/// Error details handed to the `on_error` callback
+#[derive(Clone)]
+#[napi(object)]
+pub struct PortError {
+  pub kind: PortErrorKind,
+  /// raw OS error number when the failure came from the OS
+  pub os_error_code: Option<i32>,
+  pub message: String,
+  /// path of the port the error came from
+  pub path: Option<String>,
+  /// the `tag` from the port's settings
+  pub tag: Option<String>,
+}
+
+/// Traffic counters for an open port
+#[napi(object)]
+pub struct PortStats {
+  pub bytes_read: i64,
+  pub bytes_written: i64,
+  pub read_errors: i64,
+  pub write_errors: i64,
+  /// data callbacks dropped in non-blocking mode because JS fell behind
+  pub dropped_callbacks: i64,
+}
+
+/// Everything that can be read back from an open port's OS handle, null where the platform can't tell
+#[napi(object)]
+pub struct PortDescription {
+  pub path: String,
+  pub baud_rate: u32,
+  /// read timeout in ms
+  pub timeout_ms: u32,
+  pub data_bits: DataBits,
+  pub parity: Parity,
+  pub stop_bits: StopBits,
+  pub flow_control: FlowControl,
+  /// whether other processes are kept from opening the port, null on Windows where ports are always exclusive
+  pub exclusive: Option<bool>,
+  /// whether the driver's low latency mode is on, null where the driver can't be asked (anywhere but Linux)
+  pub low_latency: Option<bool>,
+  /// bytes waiting in the OS input buffer
+  pub bytes_to_read: Option<u32>,
+  /// bytes waiting in the OS output buffer
+  pub bytes_to_write: Option<u32>,
+  pub clear_to_send: Option<bool>,
+  pub data_set_ready: Option<bool>,
+  pub carrier_detect: Option<bool>,
+  pub ring_indicator: Option<bool>,
+}
+
+/// Platform-gated features `capabilities` reports, true where they work
+#[napi(object)]
+pub struct Capabilities {
+  /// the `rs485` setting, with RTS toggled by hand where the kernel can't do it
+  pub supports_rs485: bool,
+  /// RS-485 with RTS timed by the kernel, Linux only
+  pub supports_kernel_rs485: bool,
+  /// the `low_latency` setting, Linux only
+  pub supports_low_latency: bool,
+  pub supports_break: bool,
+  /// reading CTS, DSR, CD and RI and driving RTS and DTR
+  pub supports_modem_lines: bool,
+  /// mark and space parity and `write_9bit`, Linux and Windows only
+  pub supports_mark_space_parity: bool,
+  pub supports_one_point_five_stop_bits: bool,
+  /// the `report_parity_errors` setting, Unix only
+  pub supports_parity_error_reporting: bool,
+  /// the `exclusive` setting, Windows ports are always exclusive
+  pub supports_exclusive: bool,
+  /// the `os_read_buffer_bytes` setting, Windows only
+  pub supports_os_read_buffer_size: bool,
+  /// `open_pty_pair` and `open_loopback`, Unix only
+  pub supports_pty: bool,
+  /// `by_id_path` and `by_path_path` of listed ports, Linux only
+  pub supports_by_id_paths: bool,
+}
+
+/// Criteria for `list_ports_filtered`, ports must match every field that is set
+#[napi(object)]
+pub struct PortFilter {
+  pub vid: Option<u16>,
+  pub pid: Option<u16>,
+  pub serial: Option<String>,
+  /// substring the USB manufacturer string must contain
+  pub manufacturer_contains: Option<String>,
+}
+
+/// What a port supports, as far as the OS lets us find out
+#[napi(object)]
+pub struct PortCapabilities {
+  pub port_type: String,
+  /// whether the path is a terminal device, unknown on Windows or when the path doesn't exist
+  pub is_tty: Option<bool>,
+  /// standard baud rates the driver accepts
+  pub baud_rates: Vec<u32>,
+  /// false when `baud_rates` is a generic default list rather than derived from what the driver reports
+  pub baud_rates_queried: bool,
 }
 
 // A small struct to surface USB-specific fields from SerialPortType::UsbPort
@@ -84,3 +523,132 @@ impl ToNapiValue for &mut UsbInfo {
     napi::bindgen_prelude::Object::to_napi_value(env, obj)
   }
 }
+
+// One-shot reply channel handed to a worker thread along with a command
+pub(crate) type Reply<T> = Sender<napi::Result<T>>;
+
+// Modem status lines that can be queried on an open port
+#[derive(Clone, Copy)]
+pub(crate) enum ControlLine {
+  ClearToSend,
+  DataSetReady,
+  CarrierDetect,
+  RingIndicator,
+}
+
+/// When `OpenPort::transact` considers the reply complete, it always stops at `timeout_ms`
+#[napi(object)]
+pub struct TransactOptions {
+  /// the reply ends with this delimiter, which is kept
+  pub delimiter: Option<Buffer>,
+  /// the reply is exactly this many bytes
+  pub length: Option<u32>,
+  /// give up waiting and resolve with what arrived so far after this many ms (defaults to 1000)
+  pub timeout_ms: Option<u32>,
+  /// the reply is complete once no byte arrived for this many ms after the first one
+  pub idle_complete_ms: Option<u32>,
+}
+
+// When a one-shot read is done
+pub(crate) enum ReadUntil {
+  // the first bytes to arrive, up to this many
+  AnyData(usize),
+  // exactly this many bytes, failing if they don't all arrive in time
+  Exact(usize),
+  // a full reply, or whatever arrived once the deadline passes or the line went quiet for `idle`
+  Reply {
+    delimiter: Option<Vec<u8>>,
+    length: Option<usize>,
+    idle: Option<std::time::Duration>,
+  },
+}
+
+// A one-shot read serviced by the read thread instead of `on_data_received`
+pub(crate) struct ReadRequest {
+  pub id: u64,
+  pub until: ReadUntil,
+  pub deadline: std::time::Instant,
+  pub reply: Reply<Buffer>,
+  // bytes collected so far
+  pub collected: Vec<u8>,
+  // when bytes last arrived for this request
+  pub last_data: Option<std::time::Instant>,
+  // signalled once the read thread starts routing bytes to this request
+  pub armed: Option<Sender<()>>,
+}
+
+// Exclusive use of the write thread, serviced once everything queued before it went out
+pub(crate) struct LockRequest {
+  // the only commands the write thread takes while the lock is held, until every sender is gone
+  pub rx: Receiver<Command>,
+  // resolves the holder's promise once the lock is held
+  pub grant: crate::lock::LockGrant,
+}
+
+// A write followed by a read of its reply, serviced by the write thread
+pub(crate) struct TransactRequest {
+  pub data: Buffer,
+  pub until: ReadUntil,
+  pub timeout: std::time::Duration,
+  pub reply: Reply<Buffer>,
+  pub abort: Option<std::sync::Arc<crate::abort::Abort>>,
+}
+
+// Requests that need to touch the native handle owned by a worker thread
+pub(crate) enum Command {
+  // data to write, optionally acknowledged once it has been handed to the OS
+  Write(Buffer, Option<Reply<()>>),
+  // several writes sent as one message, written back to back
+  WriteBatch(Vec<Buffer>),
+  // data written a byte at a time, pausing this long after each byte has left the port
+  WriteSlow(Buffer, std::time::Duration),
+  // 9-bit words, the 9th bit is set wherever the word has a bit of the mask set
+  Write9Bit(Vec<u16>, u16),
+  SetBaudRate(u32, Reply<()>),
+  SetFlowControl(serialport::FlowControl, Reply<()>),
+  // read timeout of the read thread's handle
+  SetTimeout(std::time::Duration, Reply<()>),
+  ReadControlLine(ControlLine, Reply<bool>),
+  SetRts(bool, Reply<()>),
+  SetDtr(bool, Reply<()>),
+  SendBreak(u32, Reply<()>),
+  // drop DTR for this many ms, then raise it again
+  PulseDtr(u32, Reply<()>),
+  SetBreak(bool, Reply<()>),
+  Clear(serialport::ClearBuffer, Reply<()>),
+  Drain(Reply<()>),
+  BytesToRead(Reply<u32>),
+  BytesToWrite(Reply<u32>),
+  CurrentSettings(Reply<PortSettings>),
+  Describe(Reply<PortDescription>),
+  RawHandle(Reply<i64>),
+  // a separate handle to the same device, for waiting on it without holding up a worker thread
+  CloneHandle(Reply<crate::open_port::NativePort>),
+  ReadOnce(ReadRequest),
+  // also queue every frame here, for `next_frame`
+  SubscribeFrames(crate::frames::FrameSender),
+  // frame incoming data with this framer from now on
+  SetFramer(Box<crate::framing::Framer>, Reply<()>),
+  // encode outgoing data like this from now on, replies with the encoding used so far
+  SetEncoding(
+    Option<crate::framing::Encoding>,
+    Reply<Option<crate::framing::Encoding>>,
+  ),
+  // throw away the partial frame the read thread is holding
+  ResetFrameBuffer(Reply<()>),
+  // service nothing but the lock holder's commands until it lets go
+  Lock(LockRequest),
+  // hand frames to this callback instead of `on_data_received` from the next one on
+  SetDataCallback(
+    std::sync::Arc<crate::open_port::OnDataReceivedCallback>,
+    Reply<()>,
+  ),
+  // report the progress of writes spanning several chunks to this callback, or stop reporting it
+  SetWriteProgress(
+    Option<std::sync::Arc<crate::open_port::OnWriteProgressCallback>>,
+    Reply<()>,
+  ),
+  // abandon the pending one-shot read with this id
+  CancelRead(u64),
+  Transact(TransactRequest),
+}
@@ -0,0 +1,195 @@
+// Notices serial ports coming and going: from udev events on Linux, by comparing successive port lists elsewhere
+
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+
+use serialport::SerialPortInfo;
+
+use std::thread;
+use std::time::Duration;
+
+use crossbeam::channel::{bounded, Receiver, RecvTimeoutError, Sender};
+
+use crate::ports::{serial_info_to_port, AvailablePort};
+
+/// A port that appeared or disappeared since the previous check
+#[napi(object, object_from_js = false)]
+pub struct PortChangeEvent {
+  /// true if the port was plugged in, false if it went away
+  pub added: bool,
+  pub port: AvailablePort,
+}
+
+/// Handle returned by `watch_ports`, keeps the watcher running until `stop` is called
+#[napi]
+pub struct PortWatcher {
+  // dropping the sender tells the watcher thread to exit
+  stop_tx: Option<Sender<()>>,
+  thread: Option<thread::JoinHandle<()>>,
+}
+
+#[napi]
+impl PortWatcher {
+  /// Stop watching, no more events are delivered once this returns
+  #[napi]
+  pub fn stop(&mut self) {
+    drop(self.stop_tx.take());
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+/// Call `on_change` whenever a serial port is added or removed.
+///
+/// On Linux the events come from udev as they happen. Elsewhere, and where udev isn't available, the port list is
+/// compared every `poll_interval_ms` (defaults to 1000). Ports present when watching starts are not reported.
+#[napi]
+pub fn watch_ports(
+  on_change: OnPortChange,
+  poll_interval_ms: Option<u32>,
+) -> napi::Result<PortWatcher> {
+  let interval = Duration::from_millis(poll_interval_ms.unwrap_or(1000).max(1) as u64);
+  let known = serialport::available_ports()
+    .map_err(|e| napi::Error::from_reason(format!("failed to list ports: {e}")))?;
+
+  let (stop_tx, stop_rx) = bounded::<()>(0);
+  let thread = thread::spawn(move || {
+    #[cfg(all(target_os = "linux", not(target_env = "musl")))]
+    let known = match udev::watch(&on_change, &stop_rx, known) {
+      Ok(()) => return,
+      // no udev to listen to, e.g. in a container
+      Err(known) => known,
+    };
+
+    poll(&on_change, &stop_rx, known, interval);
+  });
+
+  Ok(PortWatcher {
+    stop_tx: Some(stop_tx),
+    thread: Some(thread),
+  })
+}
+
+type OnPortChange = ThreadsafeFunction<PortChangeEvent, (), PortChangeEvent, napi::Status, false>;
+
+fn report(on_change: &OnPortChange, added: bool, port: SerialPortInfo) {
+  let _ = on_change.call(
+    PortChangeEvent {
+      added,
+      port: serial_info_to_port(port),
+    },
+    ThreadsafeFunctionCallMode::NonBlocking,
+  );
+}
+
+// Compare the port list every `interval` until told to stop
+fn poll(
+  on_change: &OnPortChange,
+  stop_rx: &Receiver<()>,
+  mut known: Vec<SerialPortInfo>,
+  interval: Duration,
+) {
+  while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+    // a failed listing is usually transient, try again next time
+    let Ok(current) = serialport::available_ports() else {
+      continue;
+    };
+
+    for (added, port) in diff(&known, &current) {
+      report(on_change, added, port);
+    }
+    known = current;
+  }
+}
+
+#[cfg(all(target_os = "linux", not(target_env = "musl")))]
+mod udev {
+  use std::os::unix::io::AsRawFd;
+
+  use crossbeam::channel::{Receiver, TryRecvError};
+  use serialport::SerialPortInfo;
+
+  use super::{report, OnPortChange};
+
+  // How often the stop signal is checked while no events come in
+  const STOP_CHECK_MS: i32 = 100;
+
+  // Report every tty added or removed until told to stop. Hands `known` back if udev can't be listened to.
+  pub fn watch(
+    on_change: &OnPortChange,
+    stop_rx: &Receiver<()>,
+    mut known: Vec<SerialPortInfo>,
+  ) -> Result<(), Vec<SerialPortInfo>> {
+    let Ok(context) = libudev::Context::new() else {
+      return Err(known);
+    };
+    let socket = libudev::Monitor::new(&context).and_then(|mut monitor| {
+      monitor.match_subsystem("tty")?;
+      monitor.listen()
+    });
+    let Ok(mut socket) = socket else {
+      return Err(known);
+    };
+
+    let mut poll_fd = libc::pollfd {
+      fd: socket.as_raw_fd(),
+      events: libc::POLLIN,
+      revents: 0,
+    };
+    while let Err(TryRecvError::Empty) = stop_rx.try_recv() {
+      if unsafe { libc::poll(&mut poll_fd, 1, STOP_CHECK_MS) } <= 0 {
+        continue;
+      }
+
+      while let Some(event) = socket.receive_event() {
+        let Some(path) = event.devnode().and_then(|path| path.to_str()) else {
+          continue;
+        };
+
+        match event.event_type() {
+          libudev::EventType::Remove => {
+            // only what was listed as a serial port, udev also reports virtual consoles
+            if let Some(pos) = known.iter().position(|p| p.port_name == path) {
+              report(on_change, false, known.remove(pos));
+            }
+          }
+          libudev::EventType::Add => {
+            let Ok(ports) = serialport::available_ports() else {
+              continue;
+            };
+            let Some(port) = ports.into_iter().find(|p| p.port_name == path) else {
+              continue;
+            };
+            // a remove for this path may have been missed, report the old device gone first
+            if let Some(pos) = known.iter().position(|p| p.port_name == path) {
+              report(on_change, false, known.remove(pos));
+            }
+            known.push(port.clone());
+            report(on_change, true, port);
+          }
+          _ => {}
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+// Ports that went away followed by ports that showed up, matched by path
+fn diff(before: &[SerialPortInfo], after: &[SerialPortInfo]) -> Vec<(bool, SerialPortInfo)> {
+  let missing_from =
+    |list: &[SerialPortInfo], p: &SerialPortInfo| !list.iter().any(|q| q.port_name == p.port_name);
+
+  let removed = before
+    .iter()
+    .filter(|p| missing_from(after, p))
+    .map(|p| (false, p.clone()));
+  let added = after
+    .iter()
+    .filter(|p| missing_from(before, p))
+    .map(|p| (true, p.clone()));
+
+  removed.chain(added).collect()
+}
@@ -0,0 +1,645 @@
+// The write thread: drains the write queue into the port in order
+
+use napi::bindgen_prelude::Buffer;
+use napi::threadsafe_function::ThreadsafeFunctionCallMode;
+use serialport::SerialPort;
+
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{bounded, never, Receiver, RecvError, Sender};
+
+use crate::echo::LocalEcho;
+use crate::error::ErrorSink;
+use crate::framing::Encoding;
+use crate::open_port::{
+  execute, NativePort, OnReadyCallback, OnWriteProgressCallback, SharedState,
+};
+use crate::platform::set_mark_parity;
+use crate::trace::{Direction, Trace};
+use crate::types::{
+  Command, LockRequest, PortError, PortErrorKind, ReadRequest, Reply, TransactRequest,
+  WriteProgress,
+};
+
+// Priority writes in a row before a regular write gets a chance to go out
+const PRIORITY_BURST: u32 = 16;
+
+// Least time between two progress reports of the same write
+const WRITE_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+pub(crate) struct Writer {
+  pub port: NativePort,
+  // lets transactions ask the read thread for their reply
+  pub reader_tx: Sender<Command>,
+  pub errors: Arc<ErrorSink>,
+  pub state: Arc<SharedState>,
+  // set when RTS has to be toggled by hand around every write
+  pub rs485: Option<ManualRs485>,
+  // how long a single write may stall before it is abandoned
+  pub write_timeout: Option<Duration>,
+  // packet encoding applied to every write
+  pub encoding: Option<Encoding>,
+  // set while closing with a timeout, nothing is written past it
+  pub close_deadline: Option<Instant>,
+  pub trace: Option<Arc<Trace>>,
+  // called as the write loop starts, when there is no read thread to do it
+  pub on_ready: Option<OnReadyCallback>,
+  // largest piece a write is handed to the OS in, priority writes may go out in between
+  pub chunk_size: Option<usize>,
+  // checked between chunks, the same queue `run` takes priority writes from
+  pub priority_rx: Receiver<Command>,
+  // set while a priority write is going out, so later ones can't cut into it
+  pub in_priority: bool,
+  // set when queued writes are gathered up and sent together
+  pub coalesce: Option<WriteCoalesce>,
+  // told how far writes spanning several chunks have got
+  pub on_write_progress: Option<Arc<OnWriteProgressCallback>>,
+  // set when the read thread drops the echo of what is sent
+  pub echo: Option<Arc<LocalEcho>>,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct WriteCoalesce {
+  // how long to wait for more writes after the first one
+  pub window: Duration,
+  pub max_writes: usize,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct ManualRs485 {
+  pub rts_on_send: bool,
+  pub rts_when_idle: bool,
+  pub after_send_delay: Duration,
+}
+
+impl Writer {
+  // Only plain writes set `let_priority`, anything else must go out in one piece
+  fn write(&mut self, data: &[u8], let_priority: bool) -> std::io::Result<()> {
+    match self.encoding {
+      Some(encoding) => self.send(&encoding.encode(data), let_priority),
+      None => self.send(data, let_priority),
+    }
+  }
+
+  // Write a byte at a time, waiting for each byte to leave the port and then for `delay`
+  fn write_slow(&mut self, data: &[u8], delay: Duration) -> std::io::Result<()> {
+    let encoded = self.encoding.map(|encoding| encoding.encode(data));
+    let data = encoded.as_deref().unwrap_or(data);
+
+    for byte in data.chunks(1) {
+      self.send(byte, false)?;
+      self.port.flush()?;
+      thread::sleep(delay);
+    }
+
+    Ok(())
+  }
+
+  // Transmit already encoded data in chunks, keeping the counters and trace up to date
+  fn send(&mut self, data: &[u8], let_priority: bool) -> std::io::Result<()> {
+    let chunk_size = self.chunk_size.unwrap_or(data.len()).max(1);
+    let progress = self
+      .on_write_progress
+      .clone()
+      .filter(|_| data.len() > chunk_size);
+    let mut reported = Instant::now();
+    let mut written = 0;
+    for (i, chunk) in data.chunks(chunk_size).enumerate() {
+      if i > 0 && let_priority {
+        self.let_priority_through();
+      }
+      self.send_chunk(chunk)?;
+
+      written += chunk.len();
+      if let Some(progress) = &progress {
+        if written == data.len() || reported.elapsed() >= WRITE_PROGRESS_INTERVAL {
+          reported = Instant::now();
+          let _ = progress.call(
+            WriteProgress {
+              written: written as i64,
+              total: data.len() as i64,
+            },
+            ThreadsafeFunctionCallMode::NonBlocking,
+          );
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  fn send_chunk(&mut self, data: &[u8]) -> std::io::Result<()> {
+    if let Some(echo) = &self.echo {
+      echo.record(data);
+    }
+    let res = self.transmit(data);
+    match &res {
+      Ok(()) => {
+        self.state.stats.add_written(data.len());
+        if let Some(trace) = &self.trace {
+          if let Err(e) = trace.record(Direction::Tx, data) {
+            self
+              .errors
+              .report(PortError::from_io("failed to write the trace file", &e));
+          }
+        }
+      }
+      Err(_) => {
+        self
+          .state
+          .stats
+          .write_errors
+          .fetch_add(1, Ordering::Relaxed);
+      }
+    }
+    res
+  }
+
+  fn transmit(&mut self, data: &[u8]) -> std::io::Result<()> {
+    let Some(rs485) = self.rs485 else {
+      return self.write_all(data);
+    };
+
+    // enable the transmitter, and keep it enabled until the last byte actually left
+    self.port.write_request_to_send(rs485.rts_on_send)?;
+    let res = self.write_all(data).and_then(|_| self.port.flush());
+    thread::sleep(rs485.after_send_delay);
+    self.port.write_request_to_send(rs485.rts_when_idle)?;
+
+    res
+  }
+
+  // Like `Write::write_all`, but gives up once the write timeout passes
+  fn write_all(&mut self, data: &[u8]) -> std::io::Result<()> {
+    let timeout_deadline = self.write_timeout.map(|timeout| Instant::now() + timeout);
+    let Some(deadline) = timeout_deadline
+      .into_iter()
+      .chain(self.close_deadline)
+      .min()
+    else {
+      return self.port.write_all(data);
+    };
+
+    let mut written = 0;
+    while written < data.len() {
+      match self.port.write(&data[written..]) {
+        Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+        Ok(n) => written += n,
+        // the port is stalled (e.g. CTS never asserted), keep trying until the deadline
+        Err(e)
+          if matches!(
+            e.kind(),
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::Interrupted
+          ) => {}
+        Err(e) => return Err(e),
+      }
+
+      if written < data.len() && Instant::now() >= deadline {
+        if self.close_deadline.is_some() {
+          self
+            .state
+            .dropped_on_close
+            .fetch_add((data.len() - written) as u64, Ordering::Relaxed);
+        }
+        return Err(std::io::Error::new(
+          std::io::ErrorKind::TimedOut,
+          format!(
+            "write timed out after sending {written} of {} bytes",
+            data.len()
+          ),
+        ));
+      }
+    }
+
+    Ok(())
+  }
+
+  // Send runs of words that share a 9th bit with mark parity for a set bit and space parity otherwise
+  fn write_9bit(&mut self, words: &[u16], mask: u16) -> std::io::Result<()> {
+    let mut opened_with = None;
+    let res = words
+      .chunk_by(|a, b| (a & mask != 0) == (b & mask != 0))
+      .try_for_each(|run| {
+        // waits for the previous run to leave the port before switching
+        let previous = set_mark_parity(&self.port, run[0] & mask != 0)?;
+        opened_with.get_or_insert(previous);
+        let bytes: Vec<u8> = run.iter().map(|&word| word as u8).collect();
+        self.write(&bytes, false)
+      });
+
+    if let Some(mark) = opened_with {
+      let _ = set_mark_parity(&self.port, mark);
+    }
+
+    res
+  }
+
+  // Slip queued priority writes in between two chunks of a long write, unless that would split a packet
+  fn let_priority_through(&mut self) {
+    if self.in_priority || self.encoding.is_some() {
+      return;
+    }
+
+    while let Ok(cmd) = self.priority_rx.try_recv() {
+      self.handle_priority(cmd);
+    }
+  }
+
+  fn handle_priority(&mut self, cmd: Command) {
+    self.in_priority = true;
+    self.handle(cmd);
+    self.in_priority = false;
+  }
+
+  // Write the request and wait for its reply before touching the next queued item
+  fn transact(&mut self, request: TransactRequest) {
+    let TransactRequest {
+      data,
+      until,
+      timeout,
+      reply,
+      abort,
+    } = request;
+
+    // aborted while it was waiting in the queue
+    if abort.as_ref().is_some_and(|abort| abort.is_aborted()) {
+      let _ = reply.send(Err(napi::Error::from_reason("read cancelled")));
+      return;
+    }
+
+    // hand the read thread the request before writing so a quick reply can't slip past it
+    let id = ReadRequest::next_id();
+    let (armed_tx, armed_rx) = bounded(1);
+    let (read_tx, read_rx) = bounded(1);
+    let read = ReadRequest {
+      id,
+      until,
+      deadline: Instant::now() + timeout,
+      reply: read_tx,
+      collected: Vec::new(),
+      last_data: None,
+      armed: Some(armed_tx),
+    };
+
+    if self.reader_tx.send(Command::ReadOnce(read)).is_err() {
+      let _ = reply.send(Err(napi::Error::from_reason("read thread is gone")));
+      return;
+    }
+    if armed_rx.recv().is_err() {
+      // the read thread turned the read down and said why, e.g. another read is already pending
+      let res = match read_rx.try_recv() {
+        Ok(Err(e)) => Err(e),
+        _ => Err(napi::Error::from_reason("read thread is gone")),
+      };
+      let _ = reply.send(res);
+      return;
+    }
+
+    // only attach once the read is pending, so an abort can't overtake it
+    if abort.is_some_and(|abort| !abort.attach(&self.reader_tx, id)) {
+      let _ = self.reader_tx.send(Command::CancelRead(id));
+      let _ = reply.send(Err(napi::Error::from_reason("read cancelled")));
+      return;
+    }
+
+    if let Err(e) = self.write(&data, false) {
+      let _ = self.reader_tx.send(Command::CancelRead(id));
+      let _ = reply.send(Err(napi::Error::from_reason(format!(
+        "failed to write: {e}"
+      ))));
+      return;
+    }
+
+    let res = read_rx
+      .recv()
+      .map_err(|_| napi::Error::from_reason("read thread exited before replying"))
+      .and_then(|res| res);
+    let _ = reply.send(res);
+  }
+
+  // Gather the writes queued behind this one into a single write, followed by a single flush.
+  // Returns whatever ended the batch, which goes out after it
+  fn write_coalesced(
+    &mut self,
+    coalesce: WriteCoalesce,
+    data: Buffer,
+    reply: Option<Reply<()>>,
+    write_rx: &Receiver<Command>,
+  ) -> Option<Command> {
+    let deadline = Instant::now() + coalesce.window;
+    let mut combined = Vec::new();
+    let mut replies = Vec::new();
+    let mut writes = 0;
+    // failures of writes nobody waits on go to `on_error`
+    let mut unacknowledged = false;
+    let mut next = Some(Command::Write(data, reply));
+    loop {
+      let (data, reply) = match next.take() {
+        Some(Command::Write(data, reply)) => (data, reply),
+        other => {
+          next = other;
+          break;
+        }
+      };
+      match self.encoding {
+        Some(encoding) => combined.extend(encoding.encode(&data)),
+        None => combined.extend_from_slice(&data),
+      }
+      writes += 1;
+      match reply {
+        Some(reply) => replies.push(reply),
+        None => unacknowledged = true,
+      }
+      if writes >= coalesce.max_writes {
+        break;
+      }
+      next = write_rx.recv_deadline(deadline).ok();
+    }
+
+    let res = self.send(&combined, false).and_then(|_| self.port.flush());
+    for reply in replies {
+      let _ = reply.send(
+        res
+          .as_ref()
+          .map_err(|e| napi::Error::from_reason(format!("failed to write: {e}")))
+          .copied(),
+      );
+    }
+    if let (Err(e), true) = (&res, unacknowledged) {
+      self.errors.report(PortError::from_io("failed to write", e));
+    }
+
+    next
+  }
+
+  // Service nothing but the lock holder's commands until it lets go, returns true if the port is closing meanwhile
+  fn hold_lock(
+    &mut self,
+    lock: LockRequest,
+    kill_rx: &Receiver<()>,
+    port_rx: &mut Receiver<NativePort>,
+  ) -> bool {
+    lock.grant.grant();
+
+    loop {
+      crossbeam::select! {
+        // what the holder sent before `close` still goes out
+        recv(kill_rx) -> _ => {
+          while let Ok(cmd) = lock.rx.try_recv() {
+            self.handle(cmd);
+          }
+          return true;
+        }
+        recv(port_rx) -> port => {
+          match port {
+            Ok(port) => self.port = port,
+            Err(RecvError) => *port_rx = never(),
+          }
+        }
+        recv(lock.rx) -> msg => {
+          match msg {
+            Ok(cmd) => self.handle(cmd),
+            // unlocked, or the lock was garbage collected
+            Err(RecvError) => return false,
+          }
+        }
+      }
+    }
+  }
+
+  fn handle(&mut self, cmd: Command) {
+    match cmd {
+      Command::Write(data, reply) => {
+        let res = self.write(&data, true);
+        match (res, reply) {
+          (res, Some(reply)) => {
+            let _ = reply
+              .send(res.map_err(|e| napi::Error::from_reason(format!("failed to write: {e}"))));
+          }
+          (Err(e), None) => {
+            self
+              .errors
+              .report(PortError::from_io("failed to write", &e));
+          }
+          (Ok(()), None) => {}
+        }
+      }
+      Command::WriteBatch(chunks) => {
+        for chunk in chunks {
+          // the rest would most likely fail the same way
+          if let Err(e) = self.write(&chunk, false) {
+            self
+              .errors
+              .report(PortError::from_io("failed to write", &e));
+            break;
+          }
+        }
+      }
+      Command::WriteSlow(data, delay) => {
+        if let Err(e) = self.write_slow(&data, delay) {
+          self
+            .errors
+            .report(PortError::from_io("failed to write", &e));
+        }
+      }
+      Command::Write9Bit(words, mask) => {
+        if let Err(e) = self.write_9bit(&words, mask) {
+          self
+            .errors
+            .report(PortError::from_io("failed to write 9-bit data", &e));
+        }
+      }
+      Command::Transact(request) => self.transact(request),
+      Command::SetEncoding(encoding, reply) => {
+        let previous = std::mem::replace(&mut self.encoding, encoding);
+        let _ = reply.send(Ok(previous));
+      }
+      Command::SetWriteProgress(on_write_progress, reply) => {
+        self.on_write_progress = on_write_progress;
+        let _ = reply.send(Ok(()));
+      }
+      // Control requests ordered behind the writes before them
+      cmd => execute(&mut self.port, cmd),
+    }
+  }
+
+  // Work through whatever is still queued and wait for it to leave the port
+  fn finish(&mut self, priority_rx: &Receiver<Command>, write_rx: &Receiver<Command>) {
+    self.close_deadline = *self.state.close_deadline.lock().unwrap();
+
+    while let Ok(cmd) = priority_rx.try_recv().or_else(|_| write_rx.try_recv()) {
+      if self
+        .close_deadline
+        .is_some_and(|deadline| Instant::now() >= deadline)
+      {
+        self.discard(cmd);
+      } else {
+        self.handle(cmd);
+      }
+    }
+
+    let Some(deadline) = self.close_deadline else {
+      let _ = self.port.flush();
+      return;
+    };
+
+    // unlike `flush`, this gives up on output the device won't take in time
+    loop {
+      match self.port.bytes_to_write() {
+        Ok(0) | Err(_) => break,
+        Ok(pending) if Instant::now() >= deadline => {
+          let _ = self.port.clear(serialport::ClearBuffer::Output);
+          self
+            .state
+            .dropped_on_close
+            .fetch_add(pending as u64, Ordering::Relaxed);
+          break;
+        }
+        Ok(_) => thread::sleep(Duration::from_millis(1)),
+      }
+    }
+  }
+
+  // Give up on a queued command because the close timeout ran out
+  fn discard(&mut self, cmd: Command) {
+    fn closed<T>() -> napi::Result<T> {
+      Err(napi::Error::from_reason(
+        "port closed before this was written",
+      ))
+    }
+
+    match cmd {
+      Command::Write(data, reply) => {
+        self
+          .state
+          .dropped_on_close
+          .fetch_add(data.len() as u64, Ordering::Relaxed);
+        if let Some(reply) = reply {
+          let _ = reply.send(closed());
+        }
+      }
+      Command::WriteBatch(chunks) => {
+        let len: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+        self
+          .state
+          .dropped_on_close
+          .fetch_add(len as u64, Ordering::Relaxed);
+      }
+      Command::WriteSlow(data, _) => {
+        self
+          .state
+          .dropped_on_close
+          .fetch_add(data.len() as u64, Ordering::Relaxed);
+      }
+      Command::Write9Bit(words, _) => {
+        self
+          .state
+          .dropped_on_close
+          .fetch_add(words.len() as u64, Ordering::Relaxed);
+      }
+      Command::Transact(request) => {
+        self
+          .state
+          .dropped_on_close
+          .fetch_add(request.data.len() as u64, Ordering::Relaxed);
+        let _ = request.reply.send(closed());
+      }
+      // any other reply is dropped, which fails the request
+      _ => {}
+    }
+  }
+
+  pub fn run(
+    mut self,
+    kill_rx: Receiver<()>,
+    mut priority_rx: Receiver<Command>,
+    write_rx: Receiver<Command>,
+    mut port_rx: Receiver<NativePort>,
+  ) {
+    // priority writes handled since a regular write last had a chance
+    let mut burst = 0;
+    if let Some(on_ready) = self.on_ready.take() {
+      let _ = on_ready.call((), ThreadsafeFunctionCallMode::NonBlocking);
+    }
+    loop {
+      // Priority writes jump the queue, up to a burst at a time so regular writes can't starve
+      if burst < PRIORITY_BURST {
+        if let Ok(cmd) = priority_rx.try_recv() {
+          self.handle_priority(cmd);
+          burst += 1;
+          continue;
+        }
+      }
+      burst = 0;
+
+      let mut reader_gone = false;
+      let mut priority_gone = false;
+      crossbeam::select! {
+        // Shutdown requested, but everything queued before `close` still goes out
+        recv(kill_rx) -> _ => {
+          self.finish(&priority_rx, &write_rx);
+          break;
+        }
+        // Competes fairly with regular writes right after a burst
+        recv(priority_rx) -> msg => {
+          match msg {
+            Ok(cmd) => {
+              self.handle_priority(cmd);
+              burst = 1;
+            }
+            Err(RecvError) => priority_gone = true,
+          }
+        }
+        // The read thread reconnected to the device
+        recv(port_rx) -> port => {
+          match port {
+            Ok(port) => self.port = port,
+            // the read thread is gone, no more reconnects will come
+            Err(RecvError) => reader_gone = true,
+          }
+        }
+        // Write data
+        recv(write_rx) -> msg => {
+          match msg {
+            Ok(cmd) => {
+              let cmd = match (cmd, self.coalesce) {
+                (Command::Write(data, reply), Some(coalesce)) => {
+                  self.write_coalesced(coalesce, data, reply, &write_rx)
+                }
+                (cmd, _) => Some(cmd),
+              };
+              match cmd {
+                Some(Command::Lock(lock)) => {
+                  let closing = self.hold_lock(lock, &kill_rx, &mut port_rx);
+                  if closing {
+                    self.finish(&priority_rx, &write_rx);
+                    break;
+                  }
+                }
+                Some(cmd) => self.handle(cmd),
+                None => {}
+              }
+            }
+            // channel closed, exit
+            Err(RecvError) => {
+              self.errors.report(PortError::new(PortErrorKind::Other, "write channel closed?!"));
+              break;
+            }
+          }
+        }
+      }
+
+      // stop selecting on disconnected channels so they don't spin
+      if reader_gone {
+        port_rx = never();
+      }
+      if priority_gone {
+        priority_rx = never();
+      }
+    }
+  }
+}